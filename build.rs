@@ -32,4 +32,8 @@ fn main() {
     println!("{}", String::from_utf8(out.stderr).unwrap());
     println!("{}", String::from_utf8(out.stdout).unwrap());
     println!(r"cargo:rustc-link-search=.");
+    if std::env::var("CARGO_CFG_TARGET_OS").unwrap() == "linux" {
+        // _start is ours (see main.rs), so keep crt1.o and the libc startup out.
+        println!("cargo:rustc-link-arg-bins=-nostartfiles");
+    }
 }
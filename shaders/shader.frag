@@ -2,6 +2,14 @@
 
 layout(location=0)out vec4 fragColor;
 
+// Shadertoy's inputs, so mainImage can be pasted in unchanged.
+layout(push_constant)uniform PushConstants{
+    vec3 iResolution;
+    float iTime;
+    vec4 iMouse;
+    int iFrame;
+};
+
 vec3 logo(vec2 u,float time){
     mat2 x=mat2(cos(time),sin(time),-sin(time),cos(time));
     mat2 y=mat2(sin(time),cos(time),-cos(time),sin(time));
@@ -46,11 +54,9 @@ vec3 logo(vec2 u,float time){
     return vec3((sin(u.y+time*2.)+1.)/2.,(sin(u.x+time*3.)+1.)/2.,(sin(u.y+time*5.)+1.)/2.);
 }
 
-void main(){
-    vec2 iResolution=vec2(1920.,1080.);
-    float iTime=0;
+void mainImage(out vec4 fragColor,in vec2 fragCoord){
     // Normalized pixel coordinates (from -0.5 to 0.5 vertically, -AR to AR horizontally)
-    vec2 uv=gl_FragCoord.xy/iResolution.y;
+    vec2 uv=fragCoord/iResolution.y;
     uv.x-=iResolution.x/iResolution.y/2.;
     uv.y-=.5;
     
@@ -69,4 +75,9 @@ void main(){
     
     // Output to screen
     fragColor=vec4(col,1.);
+}
+
+void main(){
+    // Shadertoy puts the origin at the bottom left, Vulkan at the top left.
+    mainImage(fragColor,vec2(gl_FragCoord.x,iResolution.y-gl_FragCoord.y));
 }
\ No newline at end of file
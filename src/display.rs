@@ -0,0 +1,236 @@
+use core::cell::Cell;
+use core::mem::MaybeUninit;
+use core::ptr;
+
+use crate::log;
+use crate::platform::{Key, Platform, Surface};
+use crate::vk::*;
+use crate::vk_check;
+
+// Presenting straight to a display plane needs no window at all, only the
+// device that drives the display. The size is whatever mode create_surface
+// ends up picking.
+pub struct Display {
+    size: Cell<(u32, u32)>,
+}
+
+impl Platform for Display {
+    const INSTANCE_EXTENSIONS: &'static [*const i8] = &[
+        "VK_KHR_surface\0".as_ptr() as *const i8,
+        "VK_KHR_display\0".as_ptr() as *const i8,
+    ];
+
+    fn create_window() -> Self {
+        Display {
+            size: Cell::new((0, 0)),
+        }
+    }
+
+    fn pump_events(&mut self) -> bool {
+        true
+    }
+
+    // The mode is fixed once the surface exists, so the size never drops to
+    // zero and there is nothing to wait for.
+    fn wait_events(&mut self) {}
+
+    // No window to take focus, but GetAsyncKeyState sees the keyboard anyway.
+    // Held keys are the top bit, as in miniwin.
+    #[cfg(windows)]
+    fn key_down(&self, key: Key) -> bool {
+        let virtual_key = match key {
+            Key::Escape => winapi::um::winuser::VK_ESCAPE,
+            Key::MouseLeft => winapi::um::winuser::VK_LBUTTON,
+        };
+        unsafe { winapi::um::winuser::GetAsyncKeyState(virtual_key) as u16 & 0x8000 != 0 }
+    }
+
+    // Without a window there is no event queue, so watch the controlling
+    // terminal for Escape instead.
+    #[cfg(target_os = "linux")]
+    fn key_down(&self, key: Key) -> bool {
+        match key {
+            Key::Escape => crate::linux::stdin_escape(),
+            Key::MouseLeft => false,
+        }
+    }
+
+    // There is no cursor on a bare display plane.
+    fn mouse_position(&self) -> (i32, i32) {
+        (0, 0)
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.size.get()
+    }
+}
+
+impl Surface for Display {
+    fn create_surface(&self, ips: &InstancePtrs, instance: Instance) -> SurfaceKHR {
+        let (surface, extent) = create_display_surface(ips, instance);
+        self.size.set((extent.width, extent.height));
+        surface
+    }
+}
+
+// Takes the first display any device can drive and a plane that can show it.
+// pick_physical_device then only accepts devices that can present to it. The
+// mode is the display's native resolution at the highest refresh on offer,
+// or whatever the display lists first if it can't do that.
+fn create_display_surface(ips: &InstancePtrs, instance: Instance) -> (SurfaceKHR, Extent2D) {
+    let (physical_device, display, native) = unsafe {
+        let mut devices: [MaybeUninit<PhysicalDevice>; crate::MAX_PHYSICAL_DEVICES] =
+            MaybeUninit::uninit_array();
+        let mut count = devices.len() as u32;
+        vk_check!(ips.EnumeratePhysicalDevices(
+            instance,
+            &mut count,
+            devices.as_mut_ptr() as *mut PhysicalDevice,
+        ));
+        let devices = MaybeUninit::slice_assume_init_ref(&devices[..count as usize]);
+        let mut found = None;
+        for &device in devices {
+            let mut displays: [MaybeUninit<DisplayPropertiesKHR>; 4] = MaybeUninit::uninit_array();
+            let mut count = displays.len() as u32;
+            vk_check!(ips.GetPhysicalDeviceDisplayPropertiesKHR(
+                device,
+                &mut count,
+                displays.as_mut_ptr() as *mut DisplayPropertiesKHR,
+            ));
+            if count > 0 {
+                let display = displays[0].assume_init_ref();
+                found = Some((device, display.display, display.physicalResolution));
+                break;
+            }
+        }
+        match found {
+            Some(found) => found,
+            None => {
+                log!("No device has a display attached\n");
+                Display::exit(1);
+            }
+        }
+    };
+
+    let mode = unsafe {
+        let mut modes: [MaybeUninit<DisplayModePropertiesKHR>; 32] = MaybeUninit::uninit_array();
+        let mut count = modes.len() as u32;
+        vk_check!(ips.GetDisplayModePropertiesKHR(
+            physical_device,
+            display,
+            &mut count,
+            modes.as_mut_ptr() as *mut DisplayModePropertiesKHR,
+        ));
+        if count == 0 {
+            log!("The display has no modes\n");
+            Display::exit(1);
+        }
+        let modes = MaybeUninit::slice_assume_init_ref(&modes[..count as usize]);
+        let is_native = |mode: &DisplayModePropertiesKHR| {
+            mode.parameters.visibleRegion.width == native.width
+                && mode.parameters.visibleRegion.height == native.height
+        };
+        let mut best = &modes[0];
+        for mode in modes {
+            if is_native(mode)
+                && (!is_native(best) || mode.parameters.refreshRate > best.parameters.refreshRate)
+            {
+                best = mode;
+            }
+        }
+        (best.displayMode, best.parameters.visibleRegion)
+    };
+
+    let (plane, stack_index) = unsafe {
+        let mut planes: [MaybeUninit<DisplayPlanePropertiesKHR>; 16] = MaybeUninit::uninit_array();
+        let mut count = planes.len() as u32;
+        vk_check!(ips.GetPhysicalDeviceDisplayPlanePropertiesKHR(
+            physical_device,
+            &mut count,
+            planes.as_mut_ptr() as *mut DisplayPlanePropertiesKHR,
+        ));
+        let planes = MaybeUninit::slice_assume_init_ref(&planes[..count as usize]);
+        let mut chosen = None;
+        'planes: for (index, plane) in planes.iter().enumerate() {
+            // Skip planes that are already showing some other display.
+            if plane.currentDisplay != DisplayKHR::NULL && plane.currentDisplay != display {
+                continue;
+            }
+            let mut supported: [MaybeUninit<DisplayKHR>; 4] = MaybeUninit::uninit_array();
+            let mut supported_count = supported.len() as u32;
+            vk_check!(ips.GetDisplayPlaneSupportedDisplaysKHR(
+                physical_device,
+                index as u32,
+                &mut supported_count,
+                supported.as_mut_ptr() as *mut DisplayKHR,
+            ));
+            for supported in &supported[..supported_count as usize] {
+                if supported.assume_init() == display {
+                    chosen = Some((index as u32, plane.currentStackIndex));
+                    break 'planes;
+                }
+            }
+        }
+        match chosen {
+            Some(chosen) => chosen,
+            None => {
+                log!("No display plane can show the display\n");
+                Display::exit(1);
+            }
+        }
+    };
+
+    let alpha_mode = unsafe {
+        let mut capabilities = MaybeUninit::uninit();
+        vk_check!(ips.GetDisplayPlaneCapabilitiesKHR(
+            physical_device,
+            mode.0,
+            plane,
+            capabilities.as_mut_ptr()
+        ));
+        let supported = capabilities.assume_init_ref().supportedAlpha;
+        match choose_alpha_mode(supported) {
+            Some(alpha_mode) => alpha_mode,
+            None => {
+                log!("The display plane supports no alpha mode\n");
+                Display::exit(1);
+            }
+        }
+    };
+
+    let create_info = DisplaySurfaceCreateInfoKHR {
+        sType: STRUCTURE_TYPE_DISPLAY_SURFACE_CREATE_INFO_KHR,
+        displayMode: mode.0,
+        planeIndex: plane,
+        planeStackIndex: stack_index,
+        transform: SURFACE_TRANSFORM_IDENTITY_BIT_KHR,
+        globalAlpha: 1f32,
+        alphaMode: alpha_mode,
+        imageExtent: mode.1,
+        pNext: ptr::null(),
+        flags: DisplaySurfaceCreateFlagsKHR::EMPTY,
+    };
+
+    let mut surface = MaybeUninit::uninit();
+    unsafe {
+        vk_check!(ips.CreateDisplayPlaneSurfaceKHR(
+            instance,
+            &create_info,
+            ptr::null(),
+            surface.as_mut_ptr()
+        ));
+        (surface.assume_init(), mode.1)
+    }
+}
+
+// A plane only has to support one of these. The shader writes an alpha of one
+// and globalAlpha is one too, so each of them shows the image as it is.
+fn choose_alpha_mode(supported: DisplayPlaneAlphaFlagsKHR) -> Option<DisplayPlaneAlphaFlagsKHR> {
+    const MODES: [DisplayPlaneAlphaFlagsKHR; 4] = [
+        DISPLAY_PLANE_ALPHA_OPAQUE_BIT_KHR,
+        DISPLAY_PLANE_ALPHA_GLOBAL_BIT_KHR,
+        DISPLAY_PLANE_ALPHA_PER_PIXEL_BIT_KHR,
+        DISPLAY_PLANE_ALPHA_PER_PIXEL_PREMULTIPLIED_BIT_KHR,
+    ];
+    MODES.iter().copied().find(|&mode| supported.contains(mode))
+}
@@ -0,0 +1,151 @@
+// Only the handful of syscalls the intro needs, each compiled in for the
+// features that use it.
+
+#[cfg(not(target_arch = "x86_64"))]
+compile_error!("the Linux port makes raw x86_64 syscalls and has its own x86_64 _start");
+
+use core::arch::asm;
+
+// x86_64 syscall numbers, see arch/x86/entry/syscalls/syscall_64.tbl
+#[cfg(feature = "display")]
+const SYS_READ: usize = 0;
+#[cfg(any(feature = "logger", feature = "headless"))]
+const SYS_WRITE: usize = 1;
+#[cfg(any(feature = "logger", feature = "headless"))]
+const SYS_OPEN: usize = 2;
+#[cfg(any(feature = "logger", feature = "headless", feature = "wayland"))]
+const SYS_CLOSE: usize = 3;
+#[cfg(any(
+    feature = "display",
+    all(feature = "wayland", not(feature = "headless"))
+))]
+const SYS_POLL: usize = 7;
+const SYS_CLOCK_GETTIME: usize = 228;
+const SYS_EXIT_GROUP: usize = 231;
+
+#[cfg(any(feature = "logger", feature = "headless"))]
+pub const O_WRONLY: usize = 1;
+#[cfg(any(feature = "logger", feature = "headless"))]
+pub const O_CREAT: usize = 0o100;
+#[cfg(feature = "headless")]
+pub const O_TRUNC: usize = 0o1000;
+#[cfg(feature = "logger")]
+pub const O_APPEND: usize = 0o2000;
+
+#[cfg(any(
+    feature = "display",
+    all(feature = "wayland", not(feature = "headless"))
+))]
+#[repr(C)]
+pub struct PollFd {
+    pub fd: i32,
+    pub events: i16,
+    pub revents: i16,
+}
+
+#[cfg(any(
+    feature = "display",
+    all(feature = "wayland", not(feature = "headless"))
+))]
+pub const POLLIN: i16 = 1;
+
+#[repr(C)]
+pub struct Timespec {
+    pub tv_sec: i64,
+    pub tv_nsec: i64,
+}
+
+pub const CLOCK_MONOTONIC: usize = 1;
+
+#[inline(always)]
+unsafe fn syscall3(n: usize, a1: usize, a2: usize, a3: usize) -> isize {
+    let ret: isize;
+    asm!(
+        "syscall",
+        inlateout("rax") n as isize => ret,
+        in("rdi") a1,
+        in("rsi") a2,
+        in("rdx") a3,
+        lateout("rcx") _,
+        lateout("r11") _,
+        options(nostack),
+    );
+    ret
+}
+
+#[cfg(feature = "display")]
+pub unsafe fn read(fd: i32, buf: *mut u8, count: usize) -> isize {
+    syscall3(SYS_READ, fd as usize, buf as usize, count)
+}
+
+#[cfg(any(feature = "logger", feature = "headless"))]
+pub unsafe fn write(fd: i32, buf: *const u8, count: usize) -> isize {
+    syscall3(SYS_WRITE, fd as usize, buf as usize, count)
+}
+
+#[cfg(any(feature = "logger", feature = "headless"))]
+pub unsafe fn open(path: *const u8, flags: usize, mode: usize) -> i32 {
+    syscall3(SYS_OPEN, path as usize, flags, mode) as i32
+}
+
+#[cfg(any(feature = "logger", feature = "headless", feature = "wayland"))]
+pub unsafe fn close(fd: i32) -> i32 {
+    syscall3(SYS_CLOSE, fd as usize, 0, 0) as i32
+}
+
+#[cfg(any(
+    feature = "display",
+    all(feature = "wayland", not(feature = "headless"))
+))]
+pub unsafe fn poll(fds: *mut PollFd, nfds: usize, timeout: i32) -> i32 {
+    syscall3(SYS_POLL, fds as usize, nfds, timeout as usize) as i32
+}
+
+pub unsafe fn clock_gettime(clock: usize, time: *mut Timespec) -> i32 {
+    syscall3(SYS_CLOCK_GETTIME, clock, time as usize, 0) as i32
+}
+
+// Non-blocking check of stdin for an Escape byte. The terminal stays in
+// canonical mode, so the line only arrives once Enter is pressed after it.
+#[cfg(feature = "display")]
+pub fn stdin_escape() -> bool {
+    // Set once stdin hits end of file, after which poll would report it
+    // readable on every call.
+    static mut STDIN_CLOSED: bool = false;
+
+    let mut fds = PollFd {
+        fd: 0,
+        events: POLLIN,
+        revents: 0,
+    };
+    unsafe {
+        if STDIN_CLOSED || poll(&mut fds, 1, 0) <= 0 {
+            return false;
+        }
+        let mut buffer = [0u8; 16];
+        let count = read(0, buffer.as_mut_ptr(), buffer.len());
+        if count == 0 {
+            STDIN_CLOSED = true;
+        }
+        let mut i = 0;
+        while i < count {
+            if buffer[i as usize] == 0x1b {
+                return true;
+            }
+            i += 1;
+        }
+        false
+    }
+}
+
+// exit_group rather than exit so any threads the drivers started go down too.
+pub fn exit(code: i32) -> ! {
+    unsafe {
+        asm!(
+            "syscall",
+            in("rax") SYS_EXIT_GROUP,
+            in("rdi") code as usize,
+            options(noreturn, nostack),
+        );
+    }
+}
@@ -4,19 +4,42 @@
 #![feature(maybe_uninit_uninit_array)]
 #![feature(maybe_uninit_slice)]
 
+#[cfg(feature = "display")]
+mod display;
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(windows)]
 mod miniwin;
+mod platform;
 mod util;
 mod vk;
-use miniwin::handle_message;
+#[cfg(all(
+    target_os = "linux",
+    feature = "wayland",
+    not(any(feature = "headless", feature = "display"))
+))]
+mod waywin;
+#[cfg(all(
+    target_os = "linux",
+    not(feature = "wayland"),
+    not(any(feature = "headless", feature = "display"))
+))]
+mod xwin;
+#[cfg(not(feature = "headless"))]
+use platform::{Key, Surface};
+use platform::{Native, Platform};
 use vk::*;
-use winapi::shared::windef::HWND;
-use winapi::um::libloaderapi::GetModuleHandleA;
 
-use core::{ffi::c_void, mem::MaybeUninit, panic::PanicInfo, ptr};
+#[cfg(feature = "logger")]
+use core::ffi::c_void;
+use core::{mem::MaybeUninit, panic::PanicInfo, ptr};
 
 #[panic_handler]
 #[no_mangle]
 pub extern "C" fn panic(_info: &PanicInfo) -> ! {
+    #[cfg(feature = "logger")]
+    util::report_panic(_info);
+    #[cfg(not(feature = "logger"))]
     loop {}
 }
 
@@ -86,43 +109,449 @@ macro_rules! create_shader_module {
             codeSize: shader.len(),
             pCode: shader.as_ptr() as *const u32,
             pNext: ptr::null(),
-            flags: 0,
+            flags: ShaderModuleCreateFlags::EMPTY,
         };
         unsafe {
             let mut shader = MaybeUninit::uninit();
-            $ps.CreateShaderModule($device, &create_info, ptr::null(), shader.as_mut_ptr());
+            vk_check!($ps.CreateShaderModule(
+                $device,
+                &create_info,
+                ptr::null(),
+                shader.as_mut_ptr()
+            ));
             shader.assume_init()
         }
     }};
 }
 
-fn init_vulkan(window: HWND, ps: &Static) {
-    let instance = create_instance(ps);
-    let surface = create_surface(ps, window, instance);
-    let physical_device = pick_physical_device(ps, instance);
-    let (device, queue) = create_logical_device(ps, physical_device);
-    let (swapchain, image) = create_swapchain(ps, device, surface);
-    let image_view = create_image_view(ps, device, image);
-    let render_pass = create_render_pass(ps, device);
-    let pipeline = create_graphics_pipeline(ps, device, render_pass);
-    let framebuffer = create_framebuffers(ps, device, image_view, render_pass);
-    let command_pool = create_command_pool(ps, device);
-    let command_buffer =
-        create_command_buffers(ps, device, command_pool, pipeline, render_pass, framebuffer);
-    let (available, rendered, fence) = create_sync_objects(ps, device);
-    draw_frame(
-        ps,
+// Shadertoy's uniforms, laid out to match the push_constant block in
+// shader.frag.
+#[repr(C)]
+struct PushConstants {
+    resolution: [f32; 3],
+    time: f32,
+    mouse: [f32; 4],
+    frame: i32,
+}
+
+// How many frames the CPU may record ahead of the GPU.
+#[cfg(not(feature = "headless"))]
+const FRAMES_IN_FLIGHT: usize = 2;
+// What we ask the swapchain for. The surface limits have the final say, and
+// anything past MAX_SWAPCHAIN_IMAGES is never looked at.
+#[cfg(not(feature = "headless"))]
+const SWAPCHAIN_IMAGES: u32 = 3;
+#[cfg(not(feature = "headless"))]
+const MAX_SWAPCHAIN_IMAGES: usize = 8;
+
+#[cfg(not(feature = "headless"))]
+#[derive(Clone, Copy)]
+struct Frame {
+    command_buffer: CommandBuffer,
+    available: Semaphore,
+    fence: Fence,
+}
+
+// Everything draw_frame touches, kept around for the life of the program.
+#[cfg(not(feature = "headless"))]
+struct Renderer {
+    #[cfg(feature = "clean_exit")]
+    instance: Instance,
+    #[cfg(all(feature = "clean_exit", feature = "logger"))]
+    messenger: DebugUtilsMessengerEXT,
+    physical_device: PhysicalDevice,
+    surface: SurfaceKHR,
+    device: Device,
+    queue_families: QueueFamilies,
+    graphics_queue: Queue,
+    present_queue: Queue,
+    render_pass: RenderPass,
+    pipeline: Pipeline,
+    pipeline_layout: PipelineLayout,
+    surface_format: SurfaceFormatKHR,
+    present_mode: PresentModeKHR,
+    #[cfg(feature = "clean_exit")]
+    command_pool: CommandPool,
+    frames: [Frame; FRAMES_IN_FLIGHT],
+    frame: usize,
+    // One per swapchain image rather than per frame. Nothing says when a
+    // present has finished waiting on its semaphore until that image is
+    // acquired again, so a frame slot can't safely reuse it.
+    rendered: [Semaphore; MAX_SWAPCHAIN_IMAGES],
+    // Everything below is rebuilt by recreate_swapchain. A null swapchain
+    // means the window is minimised and there is nothing to draw into.
+    window_size: (u32, u32),
+    out_of_date: bool,
+    swapchain: SwapchainKHR,
+    extent: Extent2D,
+    image_count: usize,
+    image_views: [ImageView; MAX_SWAPCHAIN_IMAGES],
+    framebuffers: [Framebuffer; MAX_SWAPCHAIN_IMAGES],
+}
+
+#[cfg(not(feature = "headless"))]
+fn init_vulkan<P: Surface>(platform: &P, eps: &EntryPtrs) -> (InstancePtrs, DevicePtrs, Renderer) {
+    let (instance, _validation) = create_instance::<P>(eps);
+    let ips = load_instance(eps, instance);
+    #[cfg(feature = "logger")]
+    let _messenger = create_debug_messenger(&ips, instance, _validation);
+    let surface = platform.create_surface(&ips, instance);
+    let (physical_device, queue_families) = pick_physical_device(&ips, instance, |device| {
+        find_queue_families(&ips, device, surface)
+    });
+    let surface_format = choose_surface_format(&ips, physical_device, surface);
+    let present_mode = choose_present_mode(&ips, physical_device, surface);
+    let (device, dps, graphics_queue, present_queue) =
+        create_logical_device(&ips, physical_device, &queue_families);
+    let render_pass = create_render_pass(&dps, device, surface_format.format);
+    let (pipeline, pipeline_layout) = create_graphics_pipeline(&dps, device, render_pass);
+    let command_pool = create_command_pool(&dps, device, queue_families.graphics);
+    let command_buffers = create_command_buffers::<FRAMES_IN_FLIGHT>(&dps, device, command_pool);
+    let mut frames = [Frame {
+        command_buffer: 0,
+        available: Semaphore::NULL,
+        fence: Fence::NULL,
+    }; FRAMES_IN_FLIGHT];
+    for (frame, &command_buffer) in frames.iter_mut().zip(command_buffers.iter()) {
+        *frame = Frame {
+            command_buffer,
+            available: create_semaphore(&dps, device),
+            fence: create_fence(&dps, device),
+        };
+    }
+    let mut rendered = [Semaphore::NULL; MAX_SWAPCHAIN_IMAGES];
+    for semaphore in rendered.iter_mut() {
+        *semaphore = create_semaphore(&dps, device);
+    }
+    let mut renderer = Renderer {
+        #[cfg(feature = "clean_exit")]
+        instance,
+        #[cfg(all(feature = "clean_exit", feature = "logger"))]
+        messenger: _messenger,
+        physical_device,
+        surface,
         device,
-        swapchain,
-        command_buffer,
-        queue,
-        fence,
-        available,
+        queue_families,
+        graphics_queue,
+        present_queue,
+        render_pass,
+        pipeline,
+        pipeline_layout,
+        surface_format,
+        present_mode,
+        #[cfg(feature = "clean_exit")]
+        command_pool,
+        frames,
+        frame: 0,
         rendered,
-    )
+        window_size: (0, 0),
+        out_of_date: true,
+        swapchain: SwapchainKHR::NULL,
+        extent: Extent2D {
+            width: 0,
+            height: 0,
+        },
+        image_count: 0,
+        image_views: [ImageView::NULL; MAX_SWAPCHAIN_IMAGES],
+        framebuffers: [Framebuffer::NULL; MAX_SWAPCHAIN_IMAGES],
+    };
+    recreate_swapchain(&ips, &dps, platform, &mut renderer);
+    (ips, dps, renderer)
+}
+
+// Everything init_vulkan and recreate_swapchain made, destroyed in reverse
+// order once the GPU is done with it. Compo builds just exit and let the OS
+// clean up, this is for validation-clean runs.
+#[cfg(all(feature = "clean_exit", not(feature = "headless")))]
+fn destroy_renderer(ips: &InstancePtrs, dps: &DevicePtrs, renderer: Renderer) {
+    let device = renderer.device;
+    unsafe {
+        vk_check!(dps.DeviceWaitIdle(device));
+        for i in 0..renderer.image_count {
+            dps.DestroyFramebuffer(device, renderer.framebuffers[i], ptr::null());
+            dps.DestroyImageView(device, renderer.image_views[i], ptr::null());
+        }
+        dps.DestroySwapchainKHR(device, renderer.swapchain, ptr::null());
+        for &semaphore in renderer.rendered.iter() {
+            dps.DestroySemaphore(device, semaphore, ptr::null());
+        }
+        for frame in renderer.frames.iter() {
+            dps.DestroyFence(device, frame.fence, ptr::null());
+            dps.DestroySemaphore(device, frame.available, ptr::null());
+        }
+        // Takes the command buffers with it.
+        dps.DestroyCommandPool(device, renderer.command_pool, ptr::null());
+        dps.DestroyPipeline(device, renderer.pipeline, ptr::null());
+        dps.DestroyPipelineLayout(device, renderer.pipeline_layout, ptr::null());
+        dps.DestroyRenderPass(device, renderer.render_pass, ptr::null());
+        dps.DestroyDevice(device, ptr::null());
+        ips.DestroySurfaceKHR(renderer.instance, renderer.surface, ptr::null());
+        #[cfg(feature = "logger")]
+        destroy_debug_messenger(ips, renderer.instance, renderer.messenger);
+        ips.DestroyInstance(renderer.instance, ptr::null());
+    }
+}
+
+// Throws away everything that depends on the swapchain images and builds it
+// again at the surface's current size. The old swapchain is handed over to
+// the new one so presentation can carry on without a gap. Command buffers are
+// recorded fresh every frame, so they don't need rebuilding.
+#[cfg(not(feature = "headless"))]
+fn recreate_swapchain<P: Platform>(
+    ips: &InstancePtrs,
+    dps: &DevicePtrs,
+    platform: &P,
+    renderer: &mut Renderer,
+) {
+    let device = renderer.device;
+    let old_swapchain = renderer.swapchain;
+    unsafe {
+        vk_check!(dps.DeviceWaitIdle(device));
+        for i in 0..renderer.image_count {
+            dps.DestroyFramebuffer(device, renderer.framebuffers[i], ptr::null());
+            dps.DestroyImageView(device, renderer.image_views[i], ptr::null());
+        }
+    }
+
+    let capabilities = unsafe {
+        let mut capabilities = MaybeUninit::uninit();
+        vk_check!(ips.GetPhysicalDeviceSurfaceCapabilitiesKHR(
+            renderer.physical_device,
+            renderer.surface,
+            capabilities.as_mut_ptr(),
+        ));
+        capabilities.assume_init()
+    };
+    renderer.window_size = platform.size();
+    renderer.out_of_date = false;
+    renderer.extent = surface_extent(&capabilities, renderer.window_size);
+    renderer.swapchain = SwapchainKHR::NULL;
+    renderer.image_count = 0;
+    // A zero sized swapchain can't be created, so wait for the window to
+    // come back before building a new one.
+    if renderer.extent.width != 0 && renderer.extent.height != 0 {
+        let (swapchain, images, image_count) = create_swapchain(
+            dps,
+            device,
+            renderer.surface,
+            &capabilities,
+            renderer.surface_format,
+            renderer.extent,
+            renderer.present_mode,
+            &renderer.queue_families,
+            old_swapchain,
+        );
+        renderer.swapchain = swapchain;
+        renderer.image_count = image_count;
+        for i in 0..image_count {
+            renderer.image_views[i] =
+                create_image_view(dps, device, images[i], renderer.surface_format.format);
+            renderer.framebuffers[i] = create_framebuffers(
+                dps,
+                device,
+                renderer.image_views[i],
+                renderer.render_pass,
+                renderer.extent,
+            );
+        }
+    }
+
+    if old_swapchain != SwapchainKHR::NULL {
+        unsafe {
+            dps.DestroySwapchainKHR(device, old_swapchain, ptr::null());
+        }
+    }
 }
 
-fn create_instance(ps: &Static) -> Instance {
+// The shader writes linear colour and expects the hardware to encode it, so
+// only an sRGB format looks right. Drivers differ on which channel order they
+// offer, so either will do. If neither is there, take whatever comes first.
+#[cfg(not(feature = "headless"))]
+fn choose_surface_format(
+    ips: &InstancePtrs,
+    physical_device: PhysicalDevice,
+    surface: SurfaceKHR,
+) -> SurfaceFormatKHR {
+    let mut formats = [SurfaceFormatKHR {
+        format: FORMAT_UNDEFINED,
+        colorSpace: COLOR_SPACE_SRGB_NONLINEAR_KHR,
+    }; 32];
+    let mut count = formats.len() as u32;
+    unsafe {
+        vk_check!(ips.GetPhysicalDeviceSurfaceFormatsKHR(
+            physical_device,
+            surface,
+            &mut count,
+            formats.as_mut_ptr(),
+        ));
+        // Shouldn't happen for a surface the device can present to, but
+        // there would be nothing to fall back to.
+        if count == 0 {
+            log!("The surface offers no formats\n");
+            Native::exit(1);
+        }
+    }
+    let formats = &formats[..count as usize];
+    for format in formats {
+        if (format.format == FORMAT_B8G8R8A8_SRGB || format.format == FORMAT_R8G8B8A8_SRGB)
+            && format.colorSpace == COLOR_SPACE_SRGB_NONLINEAR_KHR
+        {
+            return *format;
+        }
+    }
+    formats[0]
+}
+
+// FIFO is vsync and the only mode every driver has to support, so it's both
+// the default and the fallback. The mailbox and immediate features ask for
+// those modes instead, mailbox winning if both are on.
+#[cfg(not(feature = "headless"))]
+fn choose_present_mode(
+    ips: &InstancePtrs,
+    physical_device: PhysicalDevice,
+    surface: SurfaceKHR,
+) -> PresentModeKHR {
+    #[cfg(feature = "mailbox")]
+    const PREFERRED: PresentModeKHR = PRESENT_MODE_MAILBOX_KHR;
+    #[cfg(all(feature = "immediate", not(feature = "mailbox")))]
+    const PREFERRED: PresentModeKHR = PRESENT_MODE_IMMEDIATE_KHR;
+    #[cfg(not(any(feature = "mailbox", feature = "immediate")))]
+    const PREFERRED: PresentModeKHR = PRESENT_MODE_FIFO_KHR;
+
+    let mut modes = [PRESENT_MODE_FIFO_KHR; 8];
+    let mut count = modes.len() as u32;
+    unsafe {
+        vk_check!(ips.GetPhysicalDeviceSurfacePresentModesKHR(
+            physical_device,
+            surface,
+            &mut count,
+            modes.as_mut_ptr(),
+        ));
+    }
+    if modes[..count as usize].contains(&PREFERRED) {
+        PREFERRED
+    } else {
+        PRESENT_MODE_FIFO_KHR
+    }
+}
+
+// Most surfaces dictate their size. The ones that don't (Wayland) take
+// whatever the window is, within the limits they report. A minimised window
+// reports no size at all, while X keeps the old extent in the capabilities, so
+// that has to win.
+#[cfg(not(feature = "headless"))]
+fn surface_extent(capabilities: &SurfaceCapabilitiesKHR, window_size: (u32, u32)) -> Extent2D {
+    if window_size.0 == 0 || window_size.1 == 0 {
+        return Extent2D {
+            width: 0,
+            height: 0,
+        };
+    }
+    if capabilities.currentExtent.width != u32::MAX {
+        return capabilities.currentExtent;
+    }
+    Extent2D {
+        width: window_size
+            .0
+            .max(capabilities.minImageExtent.width)
+            .min(capabilities.maxImageExtent.width),
+        height: window_size
+            .1
+            .max(capabilities.minImageExtent.height)
+            .min(capabilities.maxImageExtent.height),
+    }
+}
+
+// Not every compositor offers opaque, some only blend. The shader writes an
+// alpha of one, so whichever mode the surface has looks the same.
+#[cfg(not(feature = "headless"))]
+fn choose_composite_alpha(supported: CompositeAlphaFlagsKHR) -> CompositeAlphaFlagsKHR {
+    const MODES: [CompositeAlphaFlagsKHR; 4] = [
+        COMPOSITE_ALPHA_OPAQUE_BIT_KHR,
+        COMPOSITE_ALPHA_PRE_MULTIPLIED_BIT_KHR,
+        COMPOSITE_ALPHA_POST_MULTIPLIED_BIT_KHR,
+        COMPOSITE_ALPHA_INHERIT_BIT_KHR,
+    ];
+    for &mode in MODES.iter() {
+        if supported.contains(mode) {
+            return mode;
+        }
+    }
+    COMPOSITE_ALPHA_OPAQUE_BIT_KHR
+}
+
+// Same render pass and pipeline as init_vulkan, but drawing into an image we
+// own instead of one borrowed from a swapchain. Needs no display server, so it
+// also runs on software drivers like lavapipe.
+#[cfg(feature = "headless")]
+fn render_offscreen<P: Platform>(platform: &P, eps: &EntryPtrs) {
+    let (width, height) = platform.size();
+    let extent = Extent2D { width, height };
+    let (instance, _validation) = create_instance::<P>(eps);
+    let ips = load_instance(eps, instance);
+    #[cfg(feature = "logger")]
+    let _messenger = create_debug_messenger(&ips, instance, _validation);
+    let (physical_device, graphics) =
+        pick_physical_device(&ips, instance, |device| find_graphics_family(&ips, device));
+    let queue_families = QueueFamilies {
+        graphics,
+        present: graphics,
+    };
+    let (device, dps, queue, _) = create_logical_device(&ips, physical_device, &queue_families);
+    let (image, _memory) = create_offscreen_image(&ips, &dps, physical_device, device, extent);
+    let (buffer, buffer_memory) =
+        create_readback_buffer(&ips, &dps, physical_device, device, extent);
+    let image_view = create_image_view(&dps, device, image, OFFSCREEN_FORMAT);
+    let render_pass = create_render_pass(&dps, device, OFFSCREEN_FORMAT);
+    let (pipeline, pipeline_layout) = create_graphics_pipeline(&dps, device, render_pass);
+    let framebuffer = create_framebuffers(&dps, device, image_view, render_pass, extent);
+    let command_pool = create_command_pool(&dps, device, graphics);
+    let command_buffers = create_command_buffers::<2>(&dps, device, command_pool);
+    record_command_buffer(
+        &dps,
+        command_buffers[0],
+        pipeline,
+        pipeline_layout,
+        render_pass,
+        framebuffer,
+        extent,
+        &PushConstants {
+            resolution: [width as f32, height as f32, 1f32],
+            time: 0f32,
+            mouse: [0f32; 4],
+            frame: 0,
+        },
+    );
+    record_readback(&dps, command_buffers[1], image, buffer, extent);
+    let fence = create_fence(&dps, device);
+    draw_offscreen(&dps, device, &command_buffers, queue, fence);
+    save_offscreen(&dps, device, buffer_memory, extent);
+
+    // Same teardown as destroy_renderer, for the objects made above.
+    #[cfg(feature = "clean_exit")]
+    unsafe {
+        vk_check!(dps.DeviceWaitIdle(device));
+        dps.DestroyFence(device, fence, ptr::null());
+        dps.DestroyCommandPool(device, command_pool, ptr::null());
+        dps.DestroyFramebuffer(device, framebuffer, ptr::null());
+        dps.DestroyPipeline(device, pipeline, ptr::null());
+        dps.DestroyPipelineLayout(device, pipeline_layout, ptr::null());
+        dps.DestroyRenderPass(device, render_pass, ptr::null());
+        dps.DestroyImageView(device, image_view, ptr::null());
+        dps.DestroyBuffer(device, buffer, ptr::null());
+        dps.FreeMemory(device, buffer_memory, ptr::null());
+        dps.DestroyImage(device, image, ptr::null());
+        dps.FreeMemory(device, _memory, ptr::null());
+        dps.DestroyDevice(device, ptr::null());
+        #[cfg(feature = "logger")]
+        destroy_debug_messenger(&ips, instance, _messenger);
+        ips.DestroyInstance(instance, ptr::null());
+    }
+}
+
+// Also says whether the validation layer got enabled, which only ever happens
+// in logger builds.
+fn create_instance<P: Platform>(eps: &EntryPtrs) -> (Instance, bool) {
     const APP_INFO: ApplicationInfo = ApplicationInfo {
         sType: STRUCTURE_TYPE_APPLICATION_INFO,
         pApplicationName: "\0".as_ptr() as *const i8,
@@ -132,76 +561,358 @@ fn create_instance(ps: &Static) -> Instance {
         apiVersion: 0b_000_0000001_0000000010_000000000000,
         pNext: ptr::null(),
     };
-
-    const EXTENSIONS: [*const i8; 2] = [
-        "VK_KHR_surface\0".as_ptr() as *const i8,
-        "VK_KHR_win32_surface\0".as_ptr() as *const i8,
-    ];
-
     const CREATE_INFO: InstanceCreateInfo = InstanceCreateInfo {
         sType: STRUCTURE_TYPE_INSTANCE_CREATE_INFO,
         pApplicationInfo: &APP_INFO,
-        enabledExtensionCount: EXTENSIONS.len() as u32,
-        ppEnabledExtensionNames: EXTENSIONS.as_ptr(),
+        enabledExtensionCount: 0,
+        ppEnabledExtensionNames: ptr::null(),
         enabledLayerCount: 0,
         ppEnabledLayerNames: ptr::null(),
         pNext: ptr::null(),
-        flags: 0,
+        flags: InstanceCreateFlags::EMPTY,
     };
+    let mut create_info = CREATE_INFO;
+    create_info.enabledExtensionCount = P::INSTANCE_EXTENSIONS.len() as u32;
+    create_info.ppEnabledExtensionNames = P::INSTANCE_EXTENSIONS.as_ptr();
+    // Logger builds run under the validation layer when it is installed, with
+    // its messages going to dbg_out.txt. Chaining the messenger in here as well
+    // catches the ones from creating the instance itself.
+    #[cfg(feature = "logger")]
+    let mut extensions = [ptr::null(); 8];
+    #[cfg(feature = "logger")]
+    let validation = has_validation_layer(eps);
+    #[cfg(not(feature = "logger"))]
+    let validation = false;
+    #[cfg(feature = "logger")]
+    {
+        if validation {
+            let count = P::INSTANCE_EXTENSIONS.len();
+            extensions[..count].copy_from_slice(P::INSTANCE_EXTENSIONS);
+            extensions[count] = "VK_EXT_debug_utils\0".as_ptr() as *const i8;
+            create_info.enabledExtensionCount += 1;
+            create_info.ppEnabledExtensionNames = extensions.as_ptr();
+            create_info.enabledLayerCount = 1;
+            create_info.ppEnabledLayerNames = &VALIDATION_LAYER;
+            create_info.pNext = &DEBUG_MESSENGER_INFO as *const _ as *const c_void;
+        } else {
+            unsafe {
+                log!("VK_LAYER_KHRONOS_validation is not installed, running without it\n");
+            }
+        }
+    }
+    let create_info = create_info;
 
     let mut instance = MaybeUninit::uninit();
     unsafe {
-        ps.CreateInstance(&CREATE_INFO, ptr::null(), instance.as_mut_ptr());
-        instance.assume_init()
+        vk_check!(eps.CreateInstance(&create_info, ptr::null(), instance.as_mut_ptr()));
+        (instance.assume_init(), validation)
     }
 }
 
-fn create_surface(ps: &Static, hwnd: HWND, instance: Instance) -> SurfaceKHR {
-    let create_info = Win32SurfaceCreateInfoKHR {
-        sType: STRUCTURE_TYPE_WIN32_SURFACE_CREATE_INFO_KHR,
-        hwnd: (hwnd as *mut _ as *mut c_void),
-        hinstance: unsafe { GetModuleHandleA(ptr::null()) as *mut _ as *mut c_void },
-        pNext: ptr::null(),
-        flags: 0,
+#[cfg(feature = "logger")]
+const VALIDATION_LAYER: *const i8 = "VK_LAYER_KHRONOS_validation\0".as_ptr() as *const i8;
+
+#[cfg(feature = "logger")]
+const DEBUG_MESSENGER_INFO: DebugUtilsMessengerCreateInfoEXT = DebugUtilsMessengerCreateInfoEXT {
+    sType: STRUCTURE_TYPE_DEBUG_UTILS_MESSENGER_CREATE_INFO_EXT,
+    pNext: ptr::null(),
+    flags: DebugUtilsMessengerCreateFlagsEXT::EMPTY,
+    messageSeverity: DEBUG_UTILS_MESSAGE_SEVERITY_WARNING_BIT_EXT
+        .union(DEBUG_UTILS_MESSAGE_SEVERITY_ERROR_BIT_EXT),
+    messageType: DEBUG_UTILS_MESSAGE_TYPE_GENERAL_BIT_EXT
+        .union(DEBUG_UTILS_MESSAGE_TYPE_VALIDATION_BIT_EXT)
+        .union(DEBUG_UTILS_MESSAGE_TYPE_PERFORMANCE_BIT_EXT),
+    pfnUserCallback: debug_callback,
+    pUserData: ptr::null_mut(),
+};
+
+#[cfg(feature = "logger")]
+fn has_validation_layer(eps: &EntryPtrs) -> bool {
+    let mut layers: [MaybeUninit<LayerProperties>; 64] = MaybeUninit::uninit_array();
+    let mut count = layers.len() as u32;
+    let layers = unsafe {
+        vk_check!(eps.EnumerateInstanceLayerProperties(
+            &mut count,
+            layers.as_mut_ptr() as *mut LayerProperties,
+        ));
+        MaybeUninit::slice_assume_init_ref(&layers[..count as usize])
     };
+    let wanted = b"VK_LAYER_KHRONOS_validation\0";
+    layers.iter().any(|layer| {
+        let name = unsafe { &*(&layer.layerName[..wanted.len()] as *const [i8] as *const [u8]) };
+        name == wanted
+    })
+}
 
-    let mut surface = MaybeUninit::uninit();
+// Only there when create_instance found the layer and enabled the extension
+// with it. Null otherwise.
+#[cfg(feature = "logger")]
+fn create_debug_messenger(
+    ips: &InstancePtrs,
+    instance: Instance,
+    validation: bool,
+) -> DebugUtilsMessengerEXT {
+    if !validation {
+        return DebugUtilsMessengerEXT::NULL;
+    }
+    let mut messenger = MaybeUninit::uninit();
     unsafe {
-        ps.CreateWin32SurfaceKHR(instance, &create_info, ptr::null(), surface.as_mut_ptr());
-        surface.assume_init()
+        vk_check!(ips.CreateDebugUtilsMessengerEXT(
+            instance,
+            &DEBUG_MESSENGER_INFO,
+            ptr::null(),
+            messenger.as_mut_ptr(),
+        ));
+        messenger.assume_init()
     }
 }
 
-fn pick_physical_device(ps: &Static, instance: Instance) -> PhysicalDevice {
-    //bounds checks are for losers, just don't plug in more than 8 gpus ok
-    let mut devices: [MaybeUninit<PhysicalDevice>; 8] = MaybeUninit::uninit_array();
-    let mut _count = MaybeUninit::uninit();
+#[cfg(feature = "logger")]
+extern "system" fn debug_callback(
+    severity: DebugUtilsMessageSeverityFlagBitsEXT,
+    message_type: DebugUtilsMessageTypeFlagsEXT,
+    data: *const DebugUtilsMessengerCallbackDataEXT,
+    _user_data: *mut c_void,
+) -> Bool32 {
+    let severity = match severity {
+        DEBUG_UTILS_MESSAGE_SEVERITY_ERROR_BIT_EXT => "[error ",
+        DEBUG_UTILS_MESSAGE_SEVERITY_WARNING_BIT_EXT => "[warning ",
+        DEBUG_UTILS_MESSAGE_SEVERITY_INFO_BIT_EXT => "[info ",
+        _ => "[verbose ",
+    };
+    let message_type = if message_type.contains(DEBUG_UTILS_MESSAGE_TYPE_VALIDATION_BIT_EXT) {
+        "validation] "
+    } else if message_type.contains(DEBUG_UTILS_MESSAGE_TYPE_PERFORMANCE_BIT_EXT) {
+        "performance] "
+    } else {
+        "general] "
+    };
     unsafe {
-        ps.EnumeratePhysicalDevices(
+        let message = (*data).pMessage as *const u8;
+        let mut length = 0;
+        while *message.add(length) != 0 {
+            length += 1;
+        }
+        crate::util::log0(severity);
+        crate::util::log0(message_type);
+        crate::util::log0(core::str::from_utf8_unchecked(core::slice::from_raw_parts(
+            message, length,
+        )));
+        crate::util::log0("\n");
+    }
+    // Returning true would abort the call that triggered the message.
+    FALSE
+}
+
+#[cfg(all(feature = "logger", feature = "clean_exit"))]
+fn destroy_debug_messenger(
+    ips: &InstancePtrs,
+    instance: Instance,
+    messenger: DebugUtilsMessengerEXT,
+) {
+    if messenger == DebugUtilsMessengerEXT::NULL {
+        return;
+    }
+    unsafe {
+        ips.DestroyDebugUtilsMessengerEXT(instance, messenger, ptr::null());
+    }
+}
+
+// Devices past this many are never looked at. The driver is told how much room
+// there is, so a bigger machine only makes it return INCOMPLETE.
+const MAX_PHYSICAL_DEVICES: usize = 16;
+const MAX_QUEUE_FAMILIES: usize = 16;
+
+// Build with TOIL_GPU set to a device index or part of a device name to skip
+// the scoring below, e.g. TOIL_GPU=1 or TOIL_GPU=NVIDIA. It is read by the
+// compiler, not at run time, so the choice is baked into the binary and
+// setting TOIL_GPU when running it does nothing.
+const GPU_OVERRIDE: Option<&str> = option_env!("TOIL_GPU");
+
+// Only devices that `queues` finds what we need on are considered. Hands back
+// the device along with whatever `queues` found for it.
+fn pick_physical_device<T>(
+    ips: &InstancePtrs,
+    instance: Instance,
+    queues: impl Fn(PhysicalDevice) -> Option<T>,
+) -> (PhysicalDevice, T) {
+    let mut devices: [MaybeUninit<PhysicalDevice>; MAX_PHYSICAL_DEVICES] =
+        MaybeUninit::uninit_array();
+    let mut count = MAX_PHYSICAL_DEVICES as u32;
+    let devices = unsafe {
+        vk_check!(ips.EnumeratePhysicalDevices(
             instance,
-            _count.as_mut_ptr(),
+            &mut count,
             devices.as_mut_ptr() as *mut PhysicalDevice,
+        ));
+        MaybeUninit::slice_assume_init_ref(&devices[..count as usize])
+    };
+
+    let mut best = None;
+    let mut best_score = 0;
+    for (index, &device) in devices.iter().enumerate() {
+        let mut properties = MaybeUninit::uninit();
+        let properties: PhysicalDeviceProperties = unsafe {
+            ips.GetPhysicalDeviceProperties(device, properties.as_mut_ptr());
+            properties.assume_init()
+        };
+        let found = match queues(device) {
+            Some(found) => found,
+            None => continue,
+        };
+        let score = match properties.deviceType {
+            PHYSICAL_DEVICE_TYPE_DISCRETE_GPU => 4,
+            PHYSICAL_DEVICE_TYPE_INTEGRATED_GPU => 3,
+            PHYSICAL_DEVICE_TYPE_VIRTUAL_GPU => 2,
+            _ => 1,
+        };
+        if let Some(wanted) = GPU_OVERRIDE {
+            if matches_override(wanted, index, &properties.deviceName) {
+                return (device, found);
+            }
+        }
+        if score > best_score {
+            best = Some((device, found));
+            best_score = score;
+        }
+    }
+
+    #[cfg(feature = "logger")]
+    if GPU_OVERRIDE.is_some() {
+        unsafe {
+            log!("TOIL_GPU matches no usable device, picking one by type\n");
+        }
+    }
+    match best {
+        Some(best) => best,
+        // Nothing here can draw, so there is no point going any further.
+        None => Native::exit(1),
+    }
+}
+
+// Which queue family we record and submit on, and which one presents. On most
+// drivers they are the same family.
+#[derive(Clone, Copy)]
+struct QueueFamilies {
+    graphics: u32,
+    present: u32,
+}
+
+fn get_queue_families<'a>(
+    ips: &InstancePtrs,
+    physical_device: PhysicalDevice,
+    families: &'a mut [MaybeUninit<QueueFamilyProperties>; MAX_QUEUE_FAMILIES],
+) -> &'a [QueueFamilyProperties] {
+    let mut count = MAX_QUEUE_FAMILIES as u32;
+    unsafe {
+        ips.GetPhysicalDeviceQueueFamilyProperties(
+            physical_device,
+            &mut count,
+            families.as_mut_ptr() as *mut QueueFamilyProperties,
         );
-        devices[0].assume_init()
+        MaybeUninit::slice_assume_init_ref(&families[..count as usize])
+    }
+}
+
+fn is_graphics_family(family: &QueueFamilyProperties) -> bool {
+    family.queueCount > 0 && family.queueFlags.contains(QUEUE_GRAPHICS_BIT)
+}
+
+#[cfg(feature = "headless")]
+fn find_graphics_family(ips: &InstancePtrs, physical_device: PhysicalDevice) -> Option<u32> {
+    let mut families = MaybeUninit::uninit_array();
+    get_queue_families(ips, physical_device, &mut families)
+        .iter()
+        .position(is_graphics_family)
+        .map(|index| index as u32)
+}
+
+// Prefers a single family that can both draw and present to the surface, and
+// only splits the work across two families when the driver leaves no choice.
+#[cfg(not(feature = "headless"))]
+fn find_queue_families(
+    ips: &InstancePtrs,
+    physical_device: PhysicalDevice,
+    surface: SurfaceKHR,
+) -> Option<QueueFamilies> {
+    let mut families = MaybeUninit::uninit_array();
+    let families = get_queue_families(ips, physical_device, &mut families);
+    let mut graphics = None;
+    let mut present = None;
+    for (index, family) in families.iter().enumerate() {
+        let index = index as u32;
+        let mut supported = FALSE;
+        unsafe {
+            vk_check!(ips.GetPhysicalDeviceSurfaceSupportKHR(
+                physical_device,
+                index,
+                surface,
+                &mut supported
+            ));
+        }
+        let can_draw = is_graphics_family(family);
+        let can_present = supported != FALSE;
+        if can_draw && can_present {
+            return Some(QueueFamilies {
+                graphics: index,
+                present: index,
+            });
+        }
+        if can_draw && graphics.is_none() {
+            graphics = Some(index);
+        }
+        if can_present && present.is_none() {
+            present = Some(index);
+        }
+    }
+    match (graphics, present) {
+        (Some(graphics), Some(present)) => Some(QueueFamilies { graphics, present }),
+        // The surface can't be shown from this device at all.
+        _ => None,
     }
 }
 
-fn create_logical_device(ps: &Static, physical_device: PhysicalDevice) -> (Device, Queue) {
-    // Here we take all infos to be const values. We can do this because most
-    // vendor cards will provide family 0 as graphics and present, and so
-    // this code assumes as such.
+// A number picks the device by its enumeration index, anything else has to
+// appear somewhere in the device name.
+fn matches_override(wanted: &str, index: usize, name: &[i8]) -> bool {
+    if let Ok(wanted) = wanted.parse::<usize>() {
+        return wanted == index;
+    }
+    let name = unsafe { &*(name as *const [i8] as *const [u8]) };
+    let length = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+    let wanted = wanted.as_bytes();
+    !wanted.is_empty()
+        && name[..length]
+            .windows(wanted.len())
+            .any(|window| window == wanted)
+}
+
+// Returns the device's own function table, then the graphics queue and the
+// present queue, which are the same queue when both come from one family.
+fn create_logical_device(
+    ips: &InstancePtrs,
+    physical_device: PhysicalDevice,
+    queue_families: &QueueFamilies,
+) -> (Device, DevicePtrs, Queue, Queue) {
     const QUEUE_CREATE_INFO: DeviceQueueCreateInfo = DeviceQueueCreateInfo {
         sType: STRUCTURE_TYPE_DEVICE_QUEUE_CREATE_INFO,
         queueFamilyIndex: 0,
         queueCount: 1,
         pQueuePriorities: &1.0,
         pNext: ptr::null(),
-        flags: 0,
+        flags: DeviceQueueCreateFlags::EMPTY,
     };
+    let mut queue_create_infos = [QUEUE_CREATE_INFO; 2];
+    queue_create_infos[0].queueFamilyIndex = queue_families.graphics;
+    queue_create_infos[1].queueFamilyIndex = queue_families.present;
+    let queue_create_infos = queue_create_infos;
+    #[cfg(feature = "headless")]
+    const DEVICE_EXTENSIONS: [*const i8; 0] = [];
+    #[cfg(not(feature = "headless"))]
     const DEVICE_EXTENSIONS: [*const i8; 1] = ["VK_KHR_swapchain\0".as_ptr() as *const i8];
     const CREATE_INFO: DeviceCreateInfo = DeviceCreateInfo {
         sType: STRUCTURE_TYPE_DEVICE_CREATE_INFO,
-        pQueueCreateInfos: &QUEUE_CREATE_INFO,
+        pQueueCreateInfos: ptr::null(),
         queueCreateInfoCount: 1,
         enabledLayerCount: 0,
         ppEnabledLayerNames: ptr::null(),
@@ -209,79 +920,278 @@ fn create_logical_device(ps: &Static, physical_device: PhysicalDevice) -> (Devic
         enabledExtensionCount: DEVICE_EXTENSIONS.len() as u32,
         pEnabledFeatures: &DEVICE_FEATURES,
         pNext: ptr::null(),
-        flags: 0,
+        flags: DeviceCreateFlags::EMPTY,
     };
+    let mut create_info = CREATE_INFO;
+    create_info.pQueueCreateInfos = queue_create_infos.as_ptr();
+    // Asking for the same family twice is an error.
+    if queue_families.present != queue_families.graphics {
+        create_info.queueCreateInfoCount = 2;
+    }
+    let create_info = create_info;
     let mut device = MaybeUninit::uninit();
-    let mut queue = MaybeUninit::uninit();
     let device = unsafe {
-        ps.CreateDevice(
+        vk_check!(ips.CreateDevice(
             physical_device,
-            &CREATE_INFO,
+            &create_info,
             ptr::null(),
             device.as_mut_ptr(),
-        );
+        ));
         device.assume_init()
     };
+    let dps = load_device(ips, device);
+    let graphics_queue = get_device_queue(&dps, device, queue_families.graphics);
+    let present_queue = get_device_queue(&dps, device, queue_families.present);
+    (device, dps, graphics_queue, present_queue)
+}
+
+fn get_device_queue(dps: &DevicePtrs, device: Device, queue_family: u32) -> Queue {
+    let mut queue = MaybeUninit::uninit();
     unsafe {
-        ps.GetDeviceQueue(device, 0, 0, queue.as_mut_ptr());
+        dps.GetDeviceQueue(device, queue_family, 0, queue.as_mut_ptr());
+        queue.assume_init()
     }
-    unsafe { (device, queue.assume_init()) }
 }
 
-fn create_swapchain(ps: &Static, device: Device, surface: SurfaceKHR) -> (SwapchainKHR, Image) {
+#[cfg(not(feature = "headless"))]
+fn create_swapchain(
+    dps: &DevicePtrs,
+    device: Device,
+    surface: SurfaceKHR,
+    capabilities: &SurfaceCapabilitiesKHR,
+    surface_format: SurfaceFormatKHR,
+    extent: Extent2D,
+    present_mode: PresentModeKHR,
+    queue_families: &QueueFamilies,
+    old_swapchain: SwapchainKHR,
+) -> (SwapchainKHR, [Image; MAX_SWAPCHAIN_IMAGES], usize) {
     let create_info;
     const CREATE_INFO: SwapchainCreateInfoKHR = SwapchainCreateInfoKHR {
         sType: STRUCTURE_TYPE_SWAPCHAIN_CREATE_INFO_KHR,
-        surface: 0,
+        surface: SurfaceKHR::NULL,
         minImageCount: 1,
-        imageFormat: FORMAT_R8G8B8A8_SRGB,
+        imageFormat: FORMAT_UNDEFINED,
         imageColorSpace: COLOR_SPACE_SRGB_NONLINEAR_KHR,
         imageExtent: Extent2D {
-            width: 1920,
-            height: 1080,
+            width: 0,
+            height: 0,
         },
-        presentMode: PRESENT_MODE_IMMEDIATE_KHR,
+        presentMode: PRESENT_MODE_FIFO_KHR,
         imageArrayLayers: 1,
         imageUsage: IMAGE_USAGE_COLOR_ATTACHMENT_BIT,
         imageSharingMode: SHARING_MODE_EXCLUSIVE,
-        queueFamilyIndexCount: 1,
-        pQueueFamilyIndices: &0,
+        queueFamilyIndexCount: 0,
+        pQueueFamilyIndices: ptr::null(),
         preTransform: SURFACE_TRANSFORM_IDENTITY_BIT_KHR,
         compositeAlpha: COMPOSITE_ALPHA_OPAQUE_BIT_KHR,
         clipped: TRUE,
-        oldSwapchain: NULL_HANDLE,
+        oldSwapchain: SwapchainKHR::NULL,
         pNext: ptr::null(),
-        flags: 0,
+        flags: SwapchainCreateFlagsKHR::EMPTY,
     };
     let mut create_info_i = CREATE_INFO;
     create_info_i.surface = surface;
+    create_info_i.minImageCount = SWAPCHAIN_IMAGES.max(capabilities.minImageCount);
+    // A max of zero means there is no limit.
+    if capabilities.maxImageCount != 0 {
+        create_info_i.minImageCount = create_info_i.minImageCount.min(capabilities.maxImageCount);
+    }
+    create_info_i.imageFormat = surface_format.format;
+    create_info_i.imageColorSpace = surface_format.colorSpace;
+    create_info_i.imageExtent = extent;
+    create_info_i.presentMode = present_mode;
+    // The current transform is always supported, identity isn't on every
+    // rotated display.
+    create_info_i.preTransform = capabilities.currentTransform;
+    create_info_i.compositeAlpha = choose_composite_alpha(capabilities.supportedCompositeAlpha);
+    // Images drawn by one family and presented by another are shared between
+    // the two, rather than handing ownership back and forth every frame.
+    let queue_family_indices = [queue_families.graphics, queue_families.present];
+    if queue_families.graphics != queue_families.present {
+        create_info_i.imageSharingMode = SHARING_MODE_CONCURRENT;
+        create_info_i.queueFamilyIndexCount = 2;
+        create_info_i.pQueueFamilyIndices = queue_family_indices.as_ptr();
+    }
+    create_info_i.oldSwapchain = old_swapchain;
     create_info = create_info_i;
     let swapchain: SwapchainKHR = unsafe {
         let mut swapchain = MaybeUninit::uninit();
-        ps.CreateSwapchainKHR(device, &create_info, ptr::null(), swapchain.as_mut_ptr());
+        vk_check!(dps.CreateSwapchainKHR(
+            device,
+            &create_info,
+            ptr::null(),
+            swapchain.as_mut_ptr()
+        ));
         swapchain.assume_init()
     };
+    let mut images = [Image::NULL; MAX_SWAPCHAIN_IMAGES];
+    let mut count = MAX_SWAPCHAIN_IMAGES as u32;
+    unsafe {
+        vk_check!(dps.GetSwapchainImagesKHR(device, swapchain, &mut count, images.as_mut_ptr()));
+    }
+    (swapchain, images, count as usize)
+}
+
+#[cfg(feature = "headless")]
+const OFFSCREEN_FORMAT: Format = FORMAT_R8G8B8A8_SRGB;
+
+#[cfg(feature = "headless")]
+fn create_offscreen_image(
+    ips: &InstancePtrs,
+    dps: &DevicePtrs,
+    physical_device: PhysicalDevice,
+    device: Device,
+    extent: Extent2D,
+) -> (Image, DeviceMemory) {
+    const CREATE_INFO: ImageCreateInfo = ImageCreateInfo {
+        sType: STRUCTURE_TYPE_IMAGE_CREATE_INFO,
+        imageType: IMAGE_TYPE_2D,
+        format: OFFSCREEN_FORMAT,
+        extent: Extent3D {
+            width: 0,
+            height: 0,
+            depth: 1,
+        },
+        mipLevels: 1,
+        arrayLayers: 1,
+        samples: SAMPLE_COUNT_1_BIT,
+        tiling: IMAGE_TILING_OPTIMAL,
+        usage: IMAGE_USAGE_COLOR_ATTACHMENT_BIT.union(IMAGE_USAGE_TRANSFER_SRC_BIT),
+        sharingMode: SHARING_MODE_EXCLUSIVE,
+        queueFamilyIndexCount: 0,
+        pQueueFamilyIndices: ptr::null(),
+        initialLayout: IMAGE_LAYOUT_UNDEFINED,
+        pNext: ptr::null(),
+        flags: ImageCreateFlags::EMPTY,
+    };
+    let mut create_info = CREATE_INFO;
+    create_info.extent.width = extent.width;
+    create_info.extent.height = extent.height;
+    let create_info = create_info;
     let image: Image = unsafe {
         let mut image = MaybeUninit::uninit();
-        let mut count = MaybeUninit::uninit();
-        ps.GetSwapchainImagesKHR(device, swapchain, count.as_mut_ptr(), image.as_mut_ptr());
-        //let count = count.assume_init();
-        //if count > 1 {
-        //    show_error("Count is greater than one\0".as_ptr() as *const i8);
-        //}
+        vk_check!(dps.CreateImage(device, &create_info, ptr::null(), image.as_mut_ptr()));
         image.assume_init()
     };
-    (swapchain, image)
+    let requirements = unsafe {
+        let mut requirements = MaybeUninit::uninit();
+        dps.GetImageMemoryRequirements(device, image, requirements.as_mut_ptr());
+        requirements.assume_init()
+    };
+    let memory = allocate_memory(
+        ips,
+        dps,
+        physical_device,
+        device,
+        &requirements,
+        MEMORY_PROPERTY_DEVICE_LOCAL_BIT,
+    );
+    unsafe {
+        vk_check!(dps.BindImageMemory(device, image, memory, 0));
+    }
+    (image, memory)
 }
 
-fn create_image_view(ps: &Static, device: Device, image: Image) -> ImageView {
+// Where the rendered image gets copied to be written out. Host coherent, so
+// reading it back needs no vkInvalidateMappedMemoryRanges.
+#[cfg(feature = "headless")]
+fn create_readback_buffer(
+    ips: &InstancePtrs,
+    dps: &DevicePtrs,
+    physical_device: PhysicalDevice,
+    device: Device,
+    extent: Extent2D,
+) -> (Buffer, DeviceMemory) {
+    const CREATE_INFO: BufferCreateInfo = BufferCreateInfo {
+        sType: STRUCTURE_TYPE_BUFFER_CREATE_INFO,
+        size: 0,
+        usage: BUFFER_USAGE_TRANSFER_DST_BIT,
+        sharingMode: SHARING_MODE_EXCLUSIVE,
+        queueFamilyIndexCount: 0,
+        pQueueFamilyIndices: ptr::null(),
+        pNext: ptr::null(),
+        flags: BufferCreateFlags::EMPTY,
+    };
+    let mut create_info = CREATE_INFO;
+    create_info.size = extent.width as DeviceSize * extent.height as DeviceSize * 4;
+    let create_info = create_info;
+    let buffer: Buffer = unsafe {
+        let mut buffer = MaybeUninit::uninit();
+        vk_check!(dps.CreateBuffer(device, &create_info, ptr::null(), buffer.as_mut_ptr()));
+        buffer.assume_init()
+    };
+    let requirements = unsafe {
+        let mut requirements = MaybeUninit::uninit();
+        dps.GetBufferMemoryRequirements(device, buffer, requirements.as_mut_ptr());
+        requirements.assume_init()
+    };
+    let memory = allocate_memory(
+        ips,
+        dps,
+        physical_device,
+        device,
+        &requirements,
+        MEMORY_PROPERTY_HOST_VISIBLE_BIT.union(MEMORY_PROPERTY_HOST_COHERENT_BIT),
+    );
+    unsafe {
+        vk_check!(dps.BindBufferMemory(device, buffer, memory, 0));
+    }
+    (buffer, memory)
+}
+
+#[cfg(feature = "headless")]
+fn allocate_memory(
+    ips: &InstancePtrs,
+    dps: &DevicePtrs,
+    physical_device: PhysicalDevice,
+    device: Device,
+    requirements: &MemoryRequirements,
+    flags: MemoryPropertyFlags,
+) -> DeviceMemory {
+    let properties = unsafe {
+        let mut properties = MaybeUninit::uninit();
+        ips.GetPhysicalDeviceMemoryProperties(physical_device, properties.as_mut_ptr());
+        properties.assume_init()
+    };
+
+    // First memory type the resource is allowed to live in that has the flags.
+    let mut memory_type = 0;
+    while memory_type < properties.memoryTypeCount {
+        if requirements.memoryTypeBits & (1 << memory_type) != 0
+            && properties.memoryTypes[memory_type as usize]
+                .propertyFlags
+                .contains(flags)
+        {
+            break;
+        }
+        memory_type += 1;
+    }
+
+    let alloc_info = MemoryAllocateInfo {
+        sType: STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO,
+        allocationSize: requirements.size,
+        memoryTypeIndex: memory_type,
+        pNext: ptr::null(),
+    };
+    unsafe {
+        if memory_type == properties.memoryTypeCount {
+            log!("No memory type has the properties needed\n");
+            Native::exit(1);
+        }
+        let mut memory = MaybeUninit::uninit();
+        vk_check!(dps.AllocateMemory(device, &alloc_info, ptr::null(), memory.as_mut_ptr()));
+        memory.assume_init()
+    }
+}
+
+fn create_image_view(dps: &DevicePtrs, device: Device, image: Image, format: Format) -> ImageView {
     let create_info;
     {
         const CREATE_INFO: ImageViewCreateInfo = ImageViewCreateInfo {
             sType: STRUCTURE_TYPE_IMAGE_VIEW_CREATE_INFO,
-            image: 0,
+            image: Image::NULL,
             viewType: IMAGE_VIEW_TYPE_2D,
-            format: FORMAT_R8G8B8A8_SRGB,
+            format: FORMAT_UNDEFINED,
             components: ComponentMapping {
                 r: COMPONENT_SWIZZLE_IDENTITY,
                 g: COMPONENT_SWIZZLE_IDENTITY,
@@ -296,30 +1206,36 @@ fn create_image_view(ps: &Static, device: Device, image: Image) -> ImageView {
                 layerCount: 1,
             },
             pNext: ptr::null(),
-            flags: 0,
+            flags: ImageViewCreateFlags::EMPTY,
         };
         let mut create_info_i = CREATE_INFO;
         create_info_i.image = image;
+        create_info_i.format = format;
         create_info = create_info_i;
     }
     unsafe {
         let mut image_view = MaybeUninit::uninit();
-        ps.CreateImageView(device, &create_info, ptr::null(), image_view.as_mut_ptr());
+        vk_check!(dps.CreateImageView(device, &create_info, ptr::null(), image_view.as_mut_ptr()));
         image_view.assume_init()
     }
 }
 
-fn create_render_pass(ps: &Static, device: Device) -> RenderPass {
+fn create_render_pass(dps: &DevicePtrs, device: Device, format: Format) -> RenderPass {
+    // PRESENT_SRC_KHR is only a valid layout with VK_KHR_swapchain enabled.
+    #[cfg(not(feature = "headless"))]
+    const FINAL_LAYOUT: ImageLayout = IMAGE_LAYOUT_PRESENT_SRC_KHR;
+    #[cfg(feature = "headless")]
+    const FINAL_LAYOUT: ImageLayout = IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL;
     const COLOR_ATTACHMENT: AttachmentDescription = AttachmentDescription {
-        format: FORMAT_R8G8B8A8_SRGB,
+        format: FORMAT_UNDEFINED,
         samples: SAMPLE_COUNT_1_BIT,
         loadOp: ATTACHMENT_LOAD_OP_CLEAR,
         storeOp: ATTACHMENT_STORE_OP_STORE,
         stencilLoadOp: ATTACHMENT_LOAD_OP_DONT_CARE,
         stencilStoreOp: ATTACHMENT_STORE_OP_DONT_CARE,
         initialLayout: IMAGE_LAYOUT_UNDEFINED,
-        finalLayout: IMAGE_LAYOUT_PRESENT_SRC_KHR,
-        flags: 0,
+        finalLayout: FINAL_LAYOUT,
+        flags: AttachmentDescriptionFlags::EMPTY,
     };
     const COLOR_ATTACHMENT_REF: AttachmentReference = AttachmentReference {
         attachment: 0,
@@ -335,52 +1251,80 @@ fn create_render_pass(ps: &Static, device: Device) -> RenderPass {
         pPreserveAttachments: ptr::null(),
         pResolveAttachments: ptr::null(),
         pDepthStencilAttachment: ptr::null(),
-        flags: 0,
+        flags: SubpassDescriptionFlags::EMPTY,
     };
     const DEPENDENCY: SubpassDependency = SubpassDependency {
         srcSubpass: SUBPASS_EXTERNAL,
         dstSubpass: 0,
         srcStageMask: PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
-        srcAccessMask: 0,
+        srcAccessMask: AccessFlags::EMPTY,
         dstStageMask: PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
         dstAccessMask: ACCESS_COLOR_ATTACHMENT_WRITE_BIT,
-        dependencyFlags: 0,
+        dependencyFlags: DependencyFlags::EMPTY,
     };
+    #[cfg(not(feature = "headless"))]
+    const DEPENDENCIES: [SubpassDependency; 1] = [DEPENDENCY];
+    // The copy to the readback buffer has to wait for the attachment writes.
+    #[cfg(feature = "headless")]
+    const DEPENDENCIES: [SubpassDependency; 2] = [
+        DEPENDENCY,
+        SubpassDependency {
+            srcSubpass: 0,
+            dstSubpass: SUBPASS_EXTERNAL,
+            srcStageMask: PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
+            srcAccessMask: ACCESS_COLOR_ATTACHMENT_WRITE_BIT,
+            dstStageMask: PIPELINE_STAGE_TRANSFER_BIT,
+            dstAccessMask: ACCESS_TRANSFER_READ_BIT,
+            dependencyFlags: DependencyFlags::EMPTY,
+        },
+    ];
     const RENDER_PASS_INFO: RenderPassCreateInfo = RenderPassCreateInfo {
         sType: STRUCTURE_TYPE_RENDER_PASS_CREATE_INFO,
         attachmentCount: 1,
-        pAttachments: &COLOR_ATTACHMENT,
+        pAttachments: ptr::null(),
         subpassCount: 1,
         pSubpasses: &SUBPASS,
-        dependencyCount: 1,
-        pDependencies: &DEPENDENCY,
+        dependencyCount: DEPENDENCIES.len() as u32,
+        pDependencies: ptr::null(),
         pNext: ptr::null(),
-        flags: 0,
+        flags: RenderPassCreateFlags::EMPTY,
     };
+    let mut color_attachment = COLOR_ATTACHMENT;
+    color_attachment.format = format;
+    let color_attachment = color_attachment;
+    let dependencies = DEPENDENCIES;
+    let mut render_pass_info = RENDER_PASS_INFO;
+    render_pass_info.pAttachments = &color_attachment;
+    render_pass_info.pDependencies = dependencies.as_ptr();
+    let render_pass_info = render_pass_info;
     unsafe {
         let mut render_pass = MaybeUninit::uninit();
-        ps.CreateRenderPass(
+        vk_check!(dps.CreateRenderPass(
             device,
-            &RENDER_PASS_INFO,
+            &render_pass_info,
             ptr::null(),
             render_pass.as_mut_ptr(),
-        );
+        ));
         render_pass.assume_init()
     }
 }
 
-fn create_graphics_pipeline(ps: &Static, device: Device, render_pass: RenderPass) -> Pipeline {
-    let vert_shader_module = create_shader_module!("../shaders/vert.spv", ps, device);
-    let frag_shader_module = create_shader_module!("../shaders/frag.spv", ps, device);
+fn create_graphics_pipeline(
+    dps: &DevicePtrs,
+    device: Device,
+    render_pass: RenderPass,
+) -> (Pipeline, PipelineLayout) {
+    let vert_shader_module = create_shader_module!("../shaders/vert.spv", dps, device);
+    let frag_shader_module = create_shader_module!("../shaders/frag.spv", dps, device);
 
     const STAGE_INFO: PipelineShaderStageCreateInfo = PipelineShaderStageCreateInfo {
         sType: STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO,
         stage: SHADER_STAGE_VERTEX_BIT,
-        module: 0,
+        module: ShaderModule::NULL,
         pName: "main\0".as_ptr() as *const i8,
         pSpecializationInfo: ptr::null(),
         pNext: ptr::null(),
-        flags: 0,
+        flags: PipelineShaderStageCreateFlags::EMPTY,
     };
 
     let mut vert_shader_stage_info = STAGE_INFO;
@@ -402,7 +1346,7 @@ fn create_graphics_pipeline(ps: &Static, device: Device, render_pass: RenderPass
             pVertexBindingDescriptions: ptr::null(),
             pVertexAttributeDescriptions: ptr::null(),
             pNext: ptr::null(),
-            flags: 0,
+            flags: PipelineVertexInputStateCreateFlags::EMPTY,
         };
     const INPUT_ASSEMBLY: PipelineInputAssemblyStateCreateInfo =
         PipelineInputAssemblyStateCreateInfo {
@@ -410,31 +1354,26 @@ fn create_graphics_pipeline(ps: &Static, device: Device, render_pass: RenderPass
             topology: PRIMITIVE_TOPOLOGY_TRIANGLE_LIST,
             primitiveRestartEnable: FALSE,
             pNext: ptr::null(),
-            flags: 0,
+            flags: PipelineInputAssemblyStateCreateFlags::EMPTY,
         };
-    const VIEWPORT: Viewport = Viewport {
-        x: 0f32,
-        y: 0f32,
-        width: 1920f32,
-        height: 1080f32,
-        minDepth: 0f32,
-        maxDepth: 1f32,
-    };
-    const SCISSOR: Rect2D = Rect2D {
-        offset: Offset2D { x: 0, y: 0 },
-        extent: Extent2D {
-            width: 1920,
-            height: 1080,
-        },
+    // Viewport and scissor are set while recording so the pipeline survives
+    // the swapchain changing size.
+    const DYNAMIC_STATES: [DynamicState; 2] = [DYNAMIC_STATE_VIEWPORT, DYNAMIC_STATE_SCISSOR];
+    const DYNAMIC_STATE: PipelineDynamicStateCreateInfo = PipelineDynamicStateCreateInfo {
+        sType: STRUCTURE_TYPE_PIPELINE_DYNAMIC_STATE_CREATE_INFO,
+        dynamicStateCount: DYNAMIC_STATES.len() as u32,
+        pDynamicStates: DYNAMIC_STATES.as_ptr(),
+        pNext: ptr::null(),
+        flags: PipelineDynamicStateCreateFlags::EMPTY,
     };
     const VIEWPORT_STATE: PipelineViewportStateCreateInfo = PipelineViewportStateCreateInfo {
         sType: STRUCTURE_TYPE_PIPELINE_VIEWPORT_STATE_CREATE_INFO,
         viewportCount: 1,
-        pViewports: &VIEWPORT,
+        pViewports: ptr::null(),
         scissorCount: 1,
-        pScissors: &SCISSOR,
+        pScissors: ptr::null(),
         pNext: ptr::null(),
-        flags: 0,
+        flags: PipelineViewportStateCreateFlags::EMPTY,
     };
     const RASTERISER: PipelineRasterizationStateCreateInfo = PipelineRasterizationStateCreateInfo {
         sType: STRUCTURE_TYPE_PIPELINE_RASTERIZATION_STATE_CREATE_INFO,
@@ -449,7 +1388,7 @@ fn create_graphics_pipeline(ps: &Static, device: Device, render_pass: RenderPass
         cullMode: CULL_MODE_BACK_BIT,
         frontFace: FRONT_FACE_CLOCKWISE,
         pNext: ptr::null(),
-        flags: 0,
+        flags: PipelineRasterizationStateCreateFlags::EMPTY,
     };
     const MULTISAMPLING: PipelineMultisampleStateCreateInfo = PipelineMultisampleStateCreateInfo {
         sType: STRUCTURE_TYPE_PIPELINE_MULTISAMPLE_STATE_CREATE_INFO,
@@ -460,21 +1399,21 @@ fn create_graphics_pipeline(ps: &Static, device: Device, render_pass: RenderPass
         alphaToOneEnable: FALSE,
         pSampleMask: ptr::null(),
         pNext: ptr::null(),
-        flags: 0,
+        flags: PipelineMultisampleStateCreateFlags::EMPTY,
     };
     const COLOR_BLEND_ATTACHMENT: PipelineColorBlendAttachmentState =
         PipelineColorBlendAttachmentState {
             colorWriteMask: COLOR_COMPONENT_R_BIT
-                | COLOR_COMPONENT_G_BIT
-                | COLOR_COMPONENT_B_BIT
-                | COLOR_COMPONENT_A_BIT,
+                .union(COLOR_COMPONENT_G_BIT)
+                .union(COLOR_COMPONENT_B_BIT)
+                .union(COLOR_COMPONENT_A_BIT),
             blendEnable: FALSE,
-            colorBlendOp: FALSE,
-            dstColorBlendFactor: FALSE,
-            srcColorBlendFactor: FALSE,
-            alphaBlendOp: FALSE,
-            dstAlphaBlendFactor: FALSE,
-            srcAlphaBlendFactor: FALSE,
+            colorBlendOp: BLEND_OP_ADD,
+            dstColorBlendFactor: BLEND_FACTOR_ZERO,
+            srcColorBlendFactor: BLEND_FACTOR_ONE,
+            alphaBlendOp: BLEND_OP_ADD,
+            dstAlphaBlendFactor: BLEND_FACTOR_ZERO,
+            srcAlphaBlendFactor: BLEND_FACTOR_ONE,
         };
     const COLOR_BLENDING: PipelineColorBlendStateCreateInfo = PipelineColorBlendStateCreateInfo {
         sType: STRUCTURE_TYPE_PIPELINE_COLOR_BLEND_STATE_CREATE_INFO,
@@ -484,25 +1423,30 @@ fn create_graphics_pipeline(ps: &Static, device: Device, render_pass: RenderPass
         pAttachments: &COLOR_BLEND_ATTACHMENT,
         blendConstants: [0f32, 0f32, 0f32, 0f32],
         pNext: ptr::null(),
-        flags: 0,
+        flags: PipelineColorBlendStateCreateFlags::EMPTY,
+    };
+    const PUSH_CONSTANT_RANGE: PushConstantRange = PushConstantRange {
+        stageFlags: SHADER_STAGE_FRAGMENT_BIT,
+        offset: 0,
+        size: core::mem::size_of::<PushConstants>() as u32,
     };
     const PIPELINE_LAYOUT_INFO: PipelineLayoutCreateInfo = PipelineLayoutCreateInfo {
         sType: STRUCTURE_TYPE_PIPELINE_LAYOUT_CREATE_INFO,
         setLayoutCount: 0,
-        pushConstantRangeCount: 0,
-        pPushConstantRanges: ptr::null(),
+        pushConstantRangeCount: 1,
+        pPushConstantRanges: &PUSH_CONSTANT_RANGE,
         pSetLayouts: ptr::null(),
         pNext: ptr::null(),
-        flags: 0,
+        flags: PipelineLayoutCreateFlags::EMPTY,
     };
     let pipeline_layout = unsafe {
         let mut pipeline_layout = MaybeUninit::uninit();
-        ps.CreatePipelineLayout(
+        vk_check!(dps.CreatePipelineLayout(
             device,
             &PIPELINE_LAYOUT_INFO,
             ptr::null(),
             pipeline_layout.as_mut_ptr(),
-        );
+        ));
         pipeline_layout.assume_init()
     };
     const PIPELINE_INFO: GraphicsPipelineCreateInfo = GraphicsPipelineCreateInfo {
@@ -515,16 +1459,16 @@ fn create_graphics_pipeline(ps: &Static, device: Device, render_pass: RenderPass
         pRasterizationState: &RASTERISER,
         pMultisampleState: &MULTISAMPLING,
         pColorBlendState: &COLOR_BLENDING,
-        layout: 0,
-        renderPass: 0,
+        layout: PipelineLayout::NULL,
+        renderPass: RenderPass::NULL,
         subpass: 0,
-        basePipelineHandle: NULL_HANDLE,
+        basePipelineHandle: Pipeline::NULL,
         basePipelineIndex: 0,
         pDepthStencilState: ptr::null(),
-        pDynamicState: ptr::null(),
+        pDynamicState: &DYNAMIC_STATE,
         pTessellationState: ptr::null(),
         pNext: ptr::null(),
-        flags: 0,
+        flags: PipelineCreateFlags::EMPTY,
     };
     let mut pipeline_info = PIPELINE_INFO;
     pipeline_info.pStages = shader_stages.as_ptr();
@@ -532,112 +1476,141 @@ fn create_graphics_pipeline(ps: &Static, device: Device, render_pass: RenderPass
     pipeline_info.renderPass = render_pass;
     let pipeline_info = pipeline_info;
 
-    unsafe {
+    let pipeline = unsafe {
         let mut pipeline = MaybeUninit::uninit();
-        ps.CreateGraphicsPipelines(
+        vk_check!(dps.CreateGraphicsPipelines(
             device,
-            NULL_HANDLE,
+            PipelineCache::NULL,
             1,
             &pipeline_info,
             ptr::null(),
             pipeline.as_mut_ptr(),
-        );
+        ));
         pipeline.assume_init()
+    };
+    // The pipeline no longer needs the modules once it has been created.
+    #[cfg(feature = "clean_exit")]
+    unsafe {
+        dps.DestroyShaderModule(device, vert_shader_module, ptr::null());
+        dps.DestroyShaderModule(device, frag_shader_module, ptr::null());
     }
+    (pipeline, pipeline_layout)
 }
 
 fn create_framebuffers(
-    ps: &Static,
+    dps: &DevicePtrs,
     device: Device,
     image_view: ImageView,
     render_pass: RenderPass,
+    extent: Extent2D,
 ) -> Framebuffer {
     const FRAMEBUFFER_INFO: FramebufferCreateInfo = FramebufferCreateInfo {
         sType: STRUCTURE_TYPE_FRAMEBUFFER_CREATE_INFO,
-        renderPass: NULL_HANDLE,
+        renderPass: RenderPass::NULL,
         attachmentCount: 1,
         pAttachments: ptr::null(),
-        width: 1920,
-        height: 1080,
+        width: 0,
+        height: 0,
         layers: 1,
         pNext: ptr::null(),
-        flags: 0,
+        flags: FramebufferCreateFlags::EMPTY,
     };
     let mut framebuffer_info = FRAMEBUFFER_INFO;
     framebuffer_info.pAttachments = &image_view;
     framebuffer_info.renderPass = render_pass;
+    framebuffer_info.width = extent.width;
+    framebuffer_info.height = extent.height;
     let framebuffer_info = framebuffer_info;
 
     unsafe {
         let mut framebuffer = MaybeUninit::uninit();
-        ps.CreateFramebuffer(
+        vk_check!(dps.CreateFramebuffer(
             device,
             &framebuffer_info,
             ptr::null(),
             framebuffer.as_mut_ptr(),
-        );
+        ));
         framebuffer.assume_init()
     }
 }
 
-fn create_command_pool(ps: &Static, device: Device) -> CommandPool {
+fn create_command_pool(dps: &DevicePtrs, device: Device, queue_family: u32) -> CommandPool {
     const POOL_INFO: CommandPoolCreateInfo = CommandPoolCreateInfo {
         sType: STRUCTURE_TYPE_COMMAND_POOL_CREATE_INFO,
         queueFamilyIndex: 0,
         pNext: ptr::null(),
-        flags: 0,
+        // The command buffer is re-recorded every frame.
+        flags: COMMAND_POOL_CREATE_RESET_COMMAND_BUFFER_BIT,
     };
+    let mut pool_info = POOL_INFO;
+    pool_info.queueFamilyIndex = queue_family;
+    let pool_info = pool_info;
     unsafe {
         let mut command_pool = MaybeUninit::uninit();
-        ps.CreateCommandPool(device, &POOL_INFO, ptr::null(), command_pool.as_mut_ptr());
+        vk_check!(dps.CreateCommandPool(
+            device,
+            &pool_info,
+            ptr::null(),
+            command_pool.as_mut_ptr()
+        ));
         command_pool.assume_init()
     }
 }
 
-fn create_command_buffers(
-    ps: &Static,
+fn create_command_buffers<const N: usize>(
+    dps: &DevicePtrs,
     device: Device,
     command_pool: CommandPool,
-    graphics_pipeline: Pipeline,
-    render_pass: RenderPass,
-    framebuffer: Framebuffer,
-) -> CommandBuffer {
+) -> [CommandBuffer; N] {
     const ALLOC_INFO: CommandBufferAllocateInfo = CommandBufferAllocateInfo {
         sType: STRUCTURE_TYPE_COMMAND_BUFFER_ALLOCATE_INFO,
-        commandPool: NULL_HANDLE,
+        commandPool: CommandPool::NULL,
         level: COMMAND_BUFFER_LEVEL_PRIMARY,
-        commandBufferCount: 1,
+        commandBufferCount: 0,
         pNext: ptr::null(),
     };
     let mut alloc_info = ALLOC_INFO;
     alloc_info.commandPool = command_pool;
+    alloc_info.commandBufferCount = N as u32;
     let alloc_info = alloc_info;
 
-    let command_buffer = unsafe {
-        let mut command_buffer = MaybeUninit::uninit();
-        ps.AllocateCommandBuffers(device, &alloc_info, command_buffer.as_mut_ptr());
-        command_buffer.assume_init()
-    };
+    let mut command_buffers = [0; N];
+    unsafe {
+        vk_check!(dps.AllocateCommandBuffers(device, &alloc_info, command_buffers.as_mut_ptr()));
+    }
+    command_buffers
+}
 
+// Begin implicitly resets the buffer, which the pool allows.
+fn record_command_buffer(
+    dps: &DevicePtrs,
+    command_buffer: CommandBuffer,
+    graphics_pipeline: Pipeline,
+    pipeline_layout: PipelineLayout,
+    render_pass: RenderPass,
+    framebuffer: Framebuffer,
+    extent: Extent2D,
+    push_constants: &PushConstants,
+) {
     const BEGIN_INFO: CommandBufferBeginInfo = CommandBufferBeginInfo {
         sType: STRUCTURE_TYPE_COMMAND_BUFFER_BEGIN_INFO,
         pInheritanceInfo: ptr::null(),
         pNext: ptr::null(),
-        flags: 0,
+        flags: CommandBufferUsageFlags::EMPTY,
     };
 
     unsafe {
-        ps.BeginCommandBuffer(command_buffer, &BEGIN_INFO);
+        vk_check!(dps.BeginCommandBuffer(command_buffer, &BEGIN_INFO));
     }
     const RENDER_PASS_INFO: RenderPassBeginInfo = RenderPassBeginInfo {
         sType: STRUCTURE_TYPE_RENDER_PASS_BEGIN_INFO,
-        renderPass: NULL_HANDLE,
-        framebuffer: NULL_HANDLE,
+        renderPass: RenderPass::NULL,
+        framebuffer: Framebuffer::NULL,
         renderArea: Rect2D {
             offset: Offset2D { x: 0, y: 0 },
             extent: Extent2D {
-                width: 1920,
-                height: 1080,
+                width: 0,
+                height: 0,
             },
         },
         pClearValues: &ClearValue {
@@ -651,87 +1624,199 @@ fn create_command_buffers(
     let mut render_pass_info = RENDER_PASS_INFO;
     render_pass_info.renderPass = render_pass;
     render_pass_info.framebuffer = framebuffer;
+    render_pass_info.renderArea.extent = extent;
     let render_pass_info = render_pass_info;
+    let viewport = Viewport {
+        x: 0f32,
+        y: 0f32,
+        width: extent.width as f32,
+        height: extent.height as f32,
+        minDepth: 0f32,
+        maxDepth: 1f32,
+    };
+    let scissor = Rect2D {
+        offset: Offset2D { x: 0, y: 0 },
+        extent,
+    };
     unsafe {
-        ps.CmdBeginRenderPass(command_buffer, &render_pass_info, SUBPASS_CONTENTS_INLINE);
-        ps.CmdBindPipeline(
+        dps.CmdBeginRenderPass(command_buffer, &render_pass_info, SUBPASS_CONTENTS_INLINE);
+        dps.CmdBindPipeline(
             command_buffer,
             PIPELINE_BIND_POINT_GRAPHICS,
             graphics_pipeline,
         );
-        ps.CmdDraw(command_buffer, 3, 1, 0, 0);
-        ps.CmdEndRenderPass(command_buffer);
-        ps.EndCommandBuffer(command_buffer);
+        dps.CmdSetViewport(command_buffer, 0, 1, &viewport);
+        dps.CmdSetScissor(command_buffer, 0, 1, &scissor);
+        dps.CmdPushConstants(
+            command_buffer,
+            pipeline_layout,
+            SHADER_STAGE_FRAGMENT_BIT,
+            0,
+            core::mem::size_of::<PushConstants>() as u32,
+            push_constants as *const PushConstants as *const core::ffi::c_void,
+        );
+        dps.CmdDraw(command_buffer, 3, 1, 0, 0);
+        dps.CmdEndRenderPass(command_buffer);
+        vk_check!(dps.EndCommandBuffer(command_buffer));
     }
-    command_buffer
 }
 
-fn create_sync_objects(ps: &Static, device: Device) -> (Semaphore, Semaphore, Fence) {
-    const SEMAPHORE_INFO: SemaphoreCreateInfo = SemaphoreCreateInfo {
-        sType: STRUCTURE_TYPE_SEMAPHORE_CREATE_INFO,
+// Recorded into its own command buffer and submitted after the frame, so
+// record_command_buffer stays the same for both paths.
+#[cfg(feature = "headless")]
+fn record_readback(
+    dps: &DevicePtrs,
+    command_buffer: CommandBuffer,
+    image: Image,
+    buffer: Buffer,
+    extent: Extent2D,
+) {
+    const BEGIN_INFO: CommandBufferBeginInfo = CommandBufferBeginInfo {
+        sType: STRUCTURE_TYPE_COMMAND_BUFFER_BEGIN_INFO,
+        pInheritanceInfo: ptr::null(),
         pNext: ptr::null(),
-        flags: 0,
+        flags: CommandBufferUsageFlags::EMPTY,
     };
-    const FENCE_INFO: FenceCreateInfo = FenceCreateInfo {
-        sType: STRUCTURE_TYPE_SEMAPHORE_CREATE_INFO,
+    // Zero row length and image height mean tightly packed rows.
+    const REGION: BufferImageCopy = BufferImageCopy {
+        bufferOffset: 0,
+        bufferRowLength: 0,
+        bufferImageHeight: 0,
+        imageSubresource: ImageSubresourceLayers {
+            aspectMask: IMAGE_ASPECT_COLOR_BIT,
+            mipLevel: 0,
+            baseArrayLayer: 0,
+            layerCount: 1,
+        },
+        imageOffset: Offset3D { x: 0, y: 0, z: 0 },
+        imageExtent: Extent3D {
+            width: 0,
+            height: 0,
+            depth: 1,
+        },
+    };
+    // Makes the copy visible to the host once the fence has signalled.
+    const BARRIER: MemoryBarrier = MemoryBarrier {
+        sType: STRUCTURE_TYPE_MEMORY_BARRIER,
+        srcAccessMask: ACCESS_TRANSFER_WRITE_BIT,
+        dstAccessMask: ACCESS_HOST_READ_BIT,
         pNext: ptr::null(),
-        flags: FENCE_CREATE_SIGNALED_BIT,
     };
+    let mut region = REGION;
+    region.imageExtent.width = extent.width;
+    region.imageExtent.height = extent.height;
+    let region = region;
     unsafe {
-        let mut available_semaphore = MaybeUninit::uninit();
-        let mut rendered_semaphore = MaybeUninit::uninit();
-        let mut in_flight_fence = MaybeUninit::uninit();
-        ps.CreateSemaphore(
-            device,
-            &SEMAPHORE_INFO,
-            ptr::null(),
-            available_semaphore.as_mut_ptr(),
+        vk_check!(dps.BeginCommandBuffer(command_buffer, &BEGIN_INFO));
+        dps.CmdCopyImageToBuffer(
+            command_buffer,
+            image,
+            IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL,
+            buffer,
+            1,
+            &region,
         );
-        ps.CreateSemaphore(
-            device,
-            &SEMAPHORE_INFO,
+        dps.CmdPipelineBarrier(
+            command_buffer,
+            PIPELINE_STAGE_TRANSFER_BIT,
+            PIPELINE_STAGE_HOST_BIT,
+            DependencyFlags::EMPTY,
+            1,
+            &BARRIER,
+            0,
+            ptr::null(),
+            0,
             ptr::null(),
-            rendered_semaphore.as_mut_ptr(),
         );
-        ps.CreateFence(
+        vk_check!(dps.EndCommandBuffer(command_buffer));
+    }
+}
+
+#[cfg(not(feature = "headless"))]
+fn create_semaphore(dps: &DevicePtrs, device: Device) -> Semaphore {
+    const SEMAPHORE_INFO: SemaphoreCreateInfo = SemaphoreCreateInfo {
+        sType: STRUCTURE_TYPE_SEMAPHORE_CREATE_INFO,
+        pNext: ptr::null(),
+        flags: SemaphoreCreateFlags::EMPTY,
+    };
+    unsafe {
+        let mut semaphore = MaybeUninit::uninit();
+        vk_check!(dps.CreateSemaphore(
             device,
-            &FENCE_INFO,
+            &SEMAPHORE_INFO,
             ptr::null(),
-            in_flight_fence.as_mut_ptr(),
-        );
-        (
-            available_semaphore.assume_init(),
-            rendered_semaphore.assume_init(),
-            in_flight_fence.assume_init(),
-        )
+            semaphore.as_mut_ptr(),
+        ));
+        semaphore.assume_init()
     }
 }
 
-fn draw_frame(
-    ps: &Static,
-    device: Device,
-    swapchain: SwapchainKHR,
-    command_buffer: CommandBuffer,
-    queue: Queue,
-    fence: Fence,
-    available_semaphore: Semaphore,
-    rendered_semaphore: Semaphore,
-) -> () {
+// Created signalled, so the first wait on it doesn't block.
+fn create_fence(dps: &DevicePtrs, device: Device) -> Fence {
+    const FENCE_INFO: FenceCreateInfo = FenceCreateInfo {
+        sType: STRUCTURE_TYPE_FENCE_CREATE_INFO,
+        pNext: ptr::null(),
+        flags: FENCE_CREATE_SIGNALED_BIT,
+    };
     unsafe {
-        ps.WaitForFences(device, 1, &fence, TRUE, u64::MAX);
+        let mut fence = MaybeUninit::uninit();
+        vk_check!(dps.CreateFence(device, &FENCE_INFO, ptr::null(), fence.as_mut_ptr()));
+        fence.assume_init()
+    }
+}
+
+#[cfg(not(feature = "headless"))]
+fn draw_frame(dps: &DevicePtrs, renderer: &mut Renderer, push_constants: &PushConstants) -> () {
+    let device = renderer.device;
+    let swapchain = renderer.swapchain;
+    let frame = renderer.frames[renderer.frame];
+    let command_buffer = frame.command_buffer;
+    let fence = frame.fence;
+    let available_semaphore = frame.available;
+
+    // The last submit from this slot, FRAMES_IN_FLIGHT frames ago, has to be
+    // done with the command buffer before it can be recorded again.
+    unsafe {
+        vk_check!(dps.WaitForFences(device, 1, &fence, TRUE, u64::MAX));
     }
     let image_index = unsafe {
         let mut image_index = MaybeUninit::uninit();
-        ps.AcquireNextImageKHR(
-            device,
-            swapchain,
-            u64::MAX,
-            available_semaphore,
-            NULL_HANDLE,
-            image_index.as_mut_ptr(),
+        let result = vk_check!(
+            dps.AcquireNextImageKHR(
+                device,
+                swapchain,
+                u64::MAX,
+                available_semaphore,
+                Fence::NULL,
+                image_index.as_mut_ptr(),
+            ),
+            ERROR_OUT_OF_DATE_KHR
         );
+        // Nothing was acquired, so leave the fence signalled for next time.
+        if result == ERROR_OUT_OF_DATE_KHR {
+            renderer.out_of_date = true;
+            return;
+        }
+        // Suboptimal still hands out an image, so draw this one and rebuild after.
+        if result == SUBOPTIMAL_KHR {
+            renderer.out_of_date = true;
+        }
         image_index.assume_init()
     };
+    let rendered_semaphore = renderer.rendered[image_index as usize];
+    unsafe {
+        vk_check!(dps.ResetFences(device, 1, &fence));
+    }
+    record_command_buffer(
+        dps,
+        command_buffer,
+        renderer.pipeline,
+        renderer.pipeline_layout,
+        renderer.render_pass,
+        renderer.framebuffers[image_index as usize],
+        renderer.extent,
+        push_constants,
+    );
     const SUBMIT_INFO: SubmitInfo = SubmitInfo {
         sType: STRUCTURE_TYPE_SUBMIT_INFO,
         waitSemaphoreCount: 1,
@@ -750,7 +1835,7 @@ fn draw_frame(
     let submit_info = submit_info;
 
     unsafe {
-        ps.QueueSubmit(queue, 1, &submit_info, fence);
+        vk_check!(dps.QueueSubmit(renderer.graphics_queue, 1, &submit_info, fence));
     }
 
     let mut result = MaybeUninit::uninit();
@@ -764,34 +1849,159 @@ fn draw_frame(
         pNext: ptr::null(),
         pResults: result.as_mut_ptr(),
     };
+    let result = unsafe {
+        vk_check!(
+            dps.QueuePresentKHR(renderer.present_queue, &present_info),
+            ERROR_OUT_OF_DATE_KHR
+        )
+    };
+    if result == ERROR_OUT_OF_DATE_KHR || result == SUBOPTIMAL_KHR {
+        renderer.out_of_date = true;
+    }
+    renderer.frame = (renderer.frame + 1) % FRAMES_IN_FLIGHT;
+}
+
+#[cfg(feature = "headless")]
+fn draw_offscreen(
+    dps: &DevicePtrs,
+    device: Device,
+    command_buffers: &[CommandBuffer],
+    queue: Queue,
+    fence: Fence,
+) -> () {
+    const SUBMIT_INFO: SubmitInfo = SubmitInfo {
+        sType: STRUCTURE_TYPE_SUBMIT_INFO,
+        waitSemaphoreCount: 0,
+        pWaitSemaphores: ptr::null(),
+        pWaitDstStageMask: ptr::null(),
+        commandBufferCount: 0,
+        pCommandBuffers: ptr::null(),
+        signalSemaphoreCount: 0,
+        pSignalSemaphores: ptr::null(),
+        pNext: ptr::null(),
+    };
+    let mut submit_info = SUBMIT_INFO;
+    submit_info.commandBufferCount = command_buffers.len() as u32;
+    submit_info.pCommandBuffers = command_buffers.as_ptr();
+    let submit_info = submit_info;
+
     unsafe {
-        ps.QueuePresentKHR(queue, &present_info);
+        vk_check!(dps.ResetFences(device, 1, &fence));
+        vk_check!(dps.QueueSubmit(queue, 1, &submit_info, fence));
+        vk_check!(dps.WaitForFences(device, 1, &fence, TRUE, u64::MAX));
     }
 }
 
-#[no_mangle]
-pub extern "system" fn mainCRTStartup() {
-    let pointers = init();
-    let (window, _hdc) = miniwin::create_window();
-    init_vulkan(window, &pointers);
-    loop {
-        {
-            if !handle_message(window) {
-                break;
-            }
+// Writes the frame to frame.ppm. The buffer is the image's bytes as they are,
+// which for an sRGB format is already what PPM viewers expect.
+#[cfg(feature = "headless")]
+fn save_offscreen(dps: &DevicePtrs, device: Device, memory: DeviceMemory, extent: Extent2D) {
+    let size = extent.width as usize * extent.height as usize * 4;
+    unsafe {
+        let mut data = MaybeUninit::uninit();
+        vk_check!(dps.MapMemory(
+            device,
+            memory,
+            0,
+            size as DeviceSize,
+            MemoryMapFlags::EMPTY,
+            data.as_mut_ptr(),
+        ));
+        let pixels = core::slice::from_raw_parts_mut(data.assume_init() as *mut u8, size);
+        util::write_ppm("frame.ppm\0", extent.width, extent.height, pixels);
+        dps.UnmapMemory(device, memory);
+    }
+}
+
+#[cfg(feature = "headless")]
+fn start(eps: &EntryPtrs) -> ! {
+    render_offscreen(&Native::create_window(), eps);
+    Native::exit(0)
+}
+
+#[cfg(not(feature = "headless"))]
+fn start(eps: &EntryPtrs) -> ! {
+    let mut platform = Native::create_window();
+    let (ips, dps, mut renderer) = init_vulkan(&platform, eps);
+    let start_time = Native::time();
+    let mut push_constants = PushConstants {
+        resolution: [0f32, 0f32, 1f32],
+        time: 0f32,
+        mouse: [0f32; 4],
+        frame: 0,
+    };
+    while platform.pump_events() && !platform.key_down(Key::Escape) {
+        if renderer.out_of_date || platform.size() != renderer.window_size {
+            recreate_swapchain(&ips, &dps, &platform, &mut renderer);
+        }
+        if renderer.swapchain == SwapchainKHR::NULL {
+            platform.wait_events();
+            continue;
         }
+        push_constants.resolution[0] = renderer.extent.width as f32;
+        push_constants.resolution[1] = renderer.extent.height as f32;
+        push_constants.time = (Native::time() - start_time) as f32;
+        update_mouse(&platform, &mut push_constants);
+        draw_frame(&dps, &mut renderer, &push_constants);
+        push_constants.frame += 1;
+    }
+    #[cfg(feature = "clean_exit")]
+    destroy_renderer(&ips, &dps, renderer);
+    Native::exit(0)
+}
 
-        unsafe {
-            if winapi::um::winuser::GetAsyncKeyState(winapi::um::winuser::VK_ESCAPE) != 0 {
-                break;
+// Same rules as Shadertoy: xy follows the pointer while the button is held,
+// zw is where it was pressed. z is negative once released and w only stays
+// positive for the frame of the click. y counts up from the bottom.
+#[cfg(not(feature = "headless"))]
+fn update_mouse<P: Platform>(platform: &P, push_constants: &mut PushConstants) {
+    let (x, y) = platform.mouse_position();
+    let (x, y) = (x as f32, push_constants.resolution[1] - y as f32);
+    let mouse = &mut push_constants.mouse;
+    if platform.key_down(Key::MouseLeft) {
+        if mouse[2] > 0f32 {
+            if mouse[3] > 0f32 {
+                mouse[3] = -mouse[3];
             }
+        } else {
+            mouse[2] = x;
+            mouse[3] = y;
+        }
+        mouse[0] = x;
+        mouse[1] = y;
+    } else {
+        if mouse[2] > 0f32 {
+            mouse[2] = -mouse[2];
+        }
+        if mouse[3] > 0f32 {
+            mouse[3] = -mouse[3];
         }
     }
+}
 
-    unsafe {
-        // Tying to exit normally seems to crash after certain APIs functions have been called. ( Like ChoosePixelFormat )
-        winapi::um::processthreadsapi::ExitProcess(0);
-    }
+#[cfg(windows)]
+#[no_mangle]
+pub extern "system" fn mainCRTStartup() {
+    start(&init())
+}
+
+// The kernel enters _start with argc on top of a 16 byte aligned stack and no
+// return address. Clear the frame pointer so backtraces stop here, and keep the
+// stack aligned across the call as the SysV ABI expects.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+core::arch::global_asm!(
+    ".globl _start",
+    "_start:",
+    "xor ebp, ebp",
+    "mov rdi, rsp",
+    "and rsp, -16",
+    "call {}",
+    sym linux_start,
+);
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+extern "C" fn linux_start(_stack: *const usize) -> ! {
+    start(&init())
 }
 
 #[no_mangle]
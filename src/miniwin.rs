@@ -2,7 +2,22 @@
 extern crate winapi;
 
 use core::cmp::Ordering;
+#[cfg(not(any(feature = "headless", feature = "display")))]
+use core::ffi::c_void;
 use core::mem::MaybeUninit;
+#[cfg(not(any(feature = "headless", feature = "display")))]
+use core::ptr;
+
+use crate::platform::REQUESTED_SIZE;
+#[cfg(not(any(feature = "headless", feature = "display")))]
+use crate::platform::{Key, Platform, Surface};
+#[cfg(not(any(feature = "headless", feature = "display")))]
+use crate::vk::{
+    Instance, InstancePtrs, SurfaceKHR, Win32SurfaceCreateFlagsKHR, Win32SurfaceCreateInfoKHR,
+    STRUCTURE_TYPE_WIN32_SURFACE_CREATE_INFO_KHR,
+};
+#[cfg(not(any(feature = "headless", feature = "display")))]
+use crate::vk_check;
 
 use winapi::um::wingdi::{
     wglCreateContext, wglMakeCurrent, ChoosePixelFormat, SetPixelFormat, SwapBuffers, DEVMODEA,
@@ -63,8 +78,12 @@ pub fn create_window() -> (HWND, HDC) {
                 | winapi::um::wingdi::DM_PELSWIDTH
                 | winapi::um::wingdi::DM_PELSHEIGHT;
             dev_mode.dmBitsPerPel = 32;
-            dev_mode.dmPelsWidth = 1920;
-            dev_mode.dmPelsHeight = 1080;
+            // Stay at the desktop resolution, the renderer picks it up from
+            // the surface.
+            dev_mode.dmPelsWidth =
+                winapi::um::winuser::GetSystemMetrics(winapi::um::winuser::SM_CXSCREEN) as u32;
+            dev_mode.dmPelsHeight =
+                winapi::um::winuser::GetSystemMetrics(winapi::um::winuser::SM_CYSCREEN) as u32;
             if winapi::um::winuser::ChangeDisplaySettingsA(
                 &mut dev_mode,
                 winapi::um::winuser::CDS_FULLSCREEN,
@@ -108,11 +127,11 @@ pub fn create_window() -> (HWND, HDC) {
                 WS_OVERLAPPEDWINDOW | WS_VISIBLE,  // dwStyle
                 CW_USEDEFAULT,
                 CW_USEDEFAULT,
-                1920,
-                1080,       // size and position
-                0 as HWND,  // hWndParent
-                0 as HMENU, // hMenu
-                hinstance,  // hInstance
+                REQUESTED_SIZE.0 as i32,
+                REQUESTED_SIZE.1 as i32, // size and position
+                0 as HWND,               // hWndParent
+                0 as HMENU,              // hMenu
+                hinstance,               // hInstance
                 0 as LPVOID,
             ); // lpParam
         }
@@ -193,6 +212,89 @@ pub fn handle_message(_window: HWND) -> bool {
     }
 }
 
+#[cfg(not(any(feature = "headless", feature = "display")))]
+pub struct Win32 {
+    pub hwnd: HWND,
+}
+
+#[cfg(not(any(feature = "headless", feature = "display")))]
+impl Platform for Win32 {
+    const INSTANCE_EXTENSIONS: &'static [*const i8] = &[
+        "VK_KHR_surface\0".as_ptr() as *const i8,
+        "VK_KHR_win32_surface\0".as_ptr() as *const i8,
+    ];
+
+    fn create_window() -> Self {
+        let (hwnd, _hdc) = create_window();
+        Win32 { hwnd }
+    }
+
+    fn pump_events(&mut self) -> bool {
+        handle_message(self.hwnd)
+    }
+
+    fn wait_events(&mut self) {
+        unsafe {
+            winapi::um::winuser::WaitMessage();
+        }
+    }
+
+    fn key_down(&self, key: Key) -> bool {
+        let virtual_key = match key {
+            Key::Escape => winapi::um::winuser::VK_ESCAPE,
+            Key::MouseLeft => winapi::um::winuser::VK_LBUTTON,
+        };
+        // Only the top bit means held. The bottom one is set by any press since
+        // the last call, which would read as a click that never happened.
+        unsafe { winapi::um::winuser::GetAsyncKeyState(virtual_key) as u16 & 0x8000 != 0 }
+    }
+
+    fn mouse_position(&self) -> (i32, i32) {
+        unsafe {
+            let mut point = core::mem::zeroed();
+            winapi::um::winuser::GetCursorPos(&mut point);
+            winapi::um::winuser::ScreenToClient(self.hwnd, &mut point);
+            (point.x, point.y)
+        }
+    }
+
+    fn size(&self) -> (u32, u32) {
+        unsafe {
+            let mut rect = core::mem::zeroed();
+            winapi::um::winuser::GetClientRect(self.hwnd, &mut rect);
+            let rect: winapi::shared::windef::RECT = rect;
+            (
+                (rect.right - rect.left) as u32,
+                (rect.bottom - rect.top) as u32,
+            )
+        }
+    }
+}
+
+#[cfg(not(any(feature = "headless", feature = "display")))]
+impl Surface for Win32 {
+    fn create_surface(&self, ips: &InstancePtrs, instance: Instance) -> SurfaceKHR {
+        let create_info = Win32SurfaceCreateInfoKHR {
+            sType: STRUCTURE_TYPE_WIN32_SURFACE_CREATE_INFO_KHR,
+            hwnd: (self.hwnd as *mut _ as *mut c_void),
+            hinstance: unsafe { GetModuleHandleA(ptr::null()) as *mut _ as *mut c_void },
+            pNext: ptr::null(),
+            flags: Win32SurfaceCreateFlagsKHR::EMPTY,
+        };
+
+        let mut surface = MaybeUninit::uninit();
+        unsafe {
+            vk_check!(ips.CreateWin32SurfaceKHR(
+                instance,
+                &create_info,
+                ptr::null(),
+                surface.as_mut_ptr()
+            ));
+            surface.assume_init()
+        }
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn memset(dest: *mut u8, c: i32, n: usize) -> *mut u8 {
     let mut i = 0;
@@ -0,0 +1,135 @@
+// Offscreen rendering only ever exits through the platform.
+#![cfg_attr(feature = "headless", allow(dead_code))]
+
+use crate::vk::{Instance, InstancePtrs, SurfaceKHR};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Key {
+    Escape,
+    MouseLeft,
+}
+
+// Everything the renderer needs from the OS. Each backend lives in its own
+// module and exactly one of them is picked as `Native` below, so main.rs never
+// has to know which one it got.
+pub trait Platform: Sized {
+    // Instance extensions create_surface relies on, VK_KHR_surface included.
+    // Empty for backends with no surface.
+    const INSTANCE_EXTENSIONS: &'static [*const i8];
+
+    fn create_window() -> Self;
+
+    // Handle pending window system events without blocking. Returns false once
+    // the user has asked to close the window.
+    fn pump_events(&mut self) -> bool;
+
+    // Block until the window system has something for pump_events, so there
+    // is no busy loop while the window is minimised and can't be drawn to.
+    fn wait_events(&mut self);
+
+    fn key_down(&self, key: Key) -> bool;
+
+    // Pointer position in window pixels, origin at the top left.
+    fn mouse_position(&self) -> (i32, i32);
+
+    // Size of the area we draw into, in pixels. Zero while minimised.
+    fn size(&self) -> (u32, u32);
+
+    // Seconds since some arbitrary fixed point, from the best clock the OS has.
+    fn time() -> f64 {
+        #[cfg(windows)]
+        unsafe {
+            use winapi::um::profileapi::{QueryPerformanceCounter, QueryPerformanceFrequency};
+            let mut frequency = core::mem::zeroed();
+            let mut counter = core::mem::zeroed();
+            QueryPerformanceFrequency(&mut frequency);
+            QueryPerformanceCounter(&mut counter);
+            *counter.QuadPart() as f64 / *frequency.QuadPart() as f64
+        }
+        #[cfg(target_os = "linux")]
+        unsafe {
+            let mut time = core::mem::MaybeUninit::uninit();
+            crate::linux::clock_gettime(crate::linux::CLOCK_MONOTONIC, time.as_mut_ptr());
+            let time = time.assume_init();
+            time.tv_sec as f64 + time.tv_nsec as f64 / 1e9
+        }
+    }
+
+    fn exit(code: i32) -> ! {
+        #[cfg(windows)]
+        unsafe {
+            // Tying to exit normally seems to crash after certain APIs functions have been called. ( Like ChoosePixelFormat )
+            winapi::um::processthreadsapi::ExitProcess(code as u32);
+            // ExitProcess never returns, but winapi doesn't declare it as -> !.
+            loop {}
+        }
+        #[cfg(target_os = "linux")]
+        crate::linux::exit(code)
+    }
+}
+
+// Backends that have something to present to. Headless only implements
+// Platform, so it can't end up in the swapchain path by accident.
+pub trait Surface: Platform {
+    fn create_surface(&self, ips: &InstancePtrs, instance: Instance) -> SurfaceKHR;
+}
+
+// What we ask for when nothing else decides the size: the initial window and
+// the offscreen image. Fullscreen and bare displays use the screen's own
+// resolution instead, and the renderer follows whatever the window ends up as.
+#[cfg_attr(any(feature = "display", feature = "fullscreen"), allow(dead_code))]
+pub const REQUESTED_SIZE: (u32, u32) = (1920, 1080);
+
+#[cfg(all(feature = "headless", feature = "display"))]
+compile_error!("the headless and display features pick different backends, enable only one");
+
+#[cfg(feature = "headless")]
+pub type Native = Headless;
+#[cfg(all(feature = "display", not(feature = "headless")))]
+pub type Native = crate::display::Display;
+#[cfg(all(windows, not(any(feature = "headless", feature = "display"))))]
+pub type Native = crate::miniwin::Win32;
+#[cfg(all(
+    target_os = "linux",
+    feature = "wayland",
+    not(any(feature = "headless", feature = "display"))
+))]
+pub type Native = crate::waywin::WlWindow;
+#[cfg(all(
+    target_os = "linux",
+    not(feature = "wayland"),
+    not(any(feature = "headless", feature = "display"))
+))]
+pub type Native = crate::xwin::XWindow;
+
+// Offscreen rendering has no window and no surface; it only needs somewhere
+// to exit through.
+#[cfg(feature = "headless")]
+pub struct Headless;
+
+#[cfg(feature = "headless")]
+impl Platform for Headless {
+    const INSTANCE_EXTENSIONS: &'static [*const i8] = &[];
+
+    fn create_window() -> Self {
+        Headless
+    }
+
+    fn pump_events(&mut self) -> bool {
+        true
+    }
+
+    fn wait_events(&mut self) {}
+
+    fn key_down(&self, _key: Key) -> bool {
+        false
+    }
+
+    fn mouse_position(&self) -> (i32, i32) {
+        (0, 0)
+    }
+
+    fn size(&self) -> (u32, u32) {
+        REQUESTED_SIZE
+    }
+}
@@ -1,9 +1,19 @@
+#[cfg(all(target_os = "linux", feature = "logger"))]
+use crate::linux;
 #[cfg(feature = "logger")]
-use winapi::um::fileapi::{CreateFileA, ReadFile, WriteFile, OPEN_ALWAYS, OPEN_EXISTING};
+use crate::platform::{Native, Platform};
 #[cfg(feature = "logger")]
-use winapi::um::handleapi::CloseHandle;
+use crate::vk;
+#[cfg(feature = "logger")]
+use core::fmt::{self, Write};
 #[cfg(feature = "logger")]
-use winapi::um::winnt::{FILE_APPEND_DATA, FILE_ATTRIBUTE_NORMAL, GENERIC_READ};
+use core::panic::PanicInfo;
+#[cfg(all(windows, feature = "logger"))]
+use winapi::um::fileapi::{CreateFileA, WriteFile, OPEN_ALWAYS};
+#[cfg(all(windows, feature = "logger"))]
+use winapi::um::handleapi::CloseHandle;
+#[cfg(all(windows, feature = "logger"))]
+use winapi::um::winnt::{FILE_APPEND_DATA, FILE_ATTRIBUTE_NORMAL};
 
 #[cfg(feature = "logger")]
 #[macro_export]
@@ -31,7 +41,30 @@ macro_rules! log {
     ($text:expr, $val1:expr, $val2:expr, $val3:expr) => {};
 }
 
+// Wraps a call through one of the function tables, like `dps.Function(...)`,
+// and hands back its vk::Result. Logger builds report any error code not
+// listed after the call and exit, everything else gets the bare call.
 #[cfg(feature = "logger")]
+#[macro_export]
+macro_rules! vk_check {
+    ($ps:ident . $function:ident ( $($arg:expr),* $(,)? ) $(, $allowed:expr)*) => {
+        crate::util::check_result(
+            $ps.$function($($arg),*),
+            concat!("vk", stringify!($function)),
+            &[$($allowed),*],
+        )
+    };
+}
+
+#[cfg(not(feature = "logger"))]
+#[macro_export]
+macro_rules! vk_check {
+    ($ps:ident . $function:ident ( $($arg:expr),* $(,)? ) $(, $allowed:expr)*) => {
+        $ps.$function($($arg),*)
+    };
+}
+
+#[cfg(all(windows, feature = "logger"))]
 pub unsafe fn log0(message: &str) {
     let name = "dbg_out.txt\0";
     let mut out = 0;
@@ -55,6 +88,118 @@ pub unsafe fn log0(message: &str) {
     CloseHandle(h_file);
 }
 
+#[cfg(all(target_os = "linux", feature = "logger"))]
+pub unsafe fn log0(message: &str) {
+    let fd = linux::open(
+        "dbg_out.txt\0".as_ptr(),
+        linux::O_WRONLY | linux::O_CREAT | linux::O_APPEND,
+        0o644,
+    );
+    linux::write(fd, message.as_ptr(), message.len());
+    linux::close(fd);
+}
+
+// Creates or truncates the file and writes the parts to it back to back.
+#[cfg(all(windows, feature = "headless"))]
+pub unsafe fn write_file(file_name: &str, parts: &[&[u8]]) {
+    use winapi::um::fileapi::{CreateFileA, WriteFile, CREATE_ALWAYS};
+    use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+    use winapi::um::winnt::{FILE_ATTRIBUTE_NORMAL, GENERIC_WRITE};
+
+    let h_file = CreateFileA(
+        file_name.as_ptr() as *const i8,
+        GENERIC_WRITE,
+        0,
+        0 as *mut winapi::um::minwinbase::SECURITY_ATTRIBUTES,
+        CREATE_ALWAYS,
+        FILE_ATTRIBUTE_NORMAL,
+        0 as *mut winapi::ctypes::c_void,
+    );
+    if h_file == INVALID_HANDLE_VALUE {
+        log!("Can't create the output file\n");
+        return;
+    }
+    for part in parts {
+        let mut out = 0;
+        WriteFile(
+            h_file,
+            part.as_ptr() as *const winapi::ctypes::c_void,
+            part.len() as u32,
+            &mut out,
+            0 as *mut winapi::um::minwinbase::OVERLAPPED,
+        );
+    }
+    CloseHandle(h_file);
+}
+
+#[cfg(all(target_os = "linux", feature = "headless"))]
+pub unsafe fn write_file(file_name: &str, parts: &[&[u8]]) {
+    use crate::linux;
+
+    let fd = linux::open(
+        file_name.as_ptr(),
+        linux::O_WRONLY | linux::O_CREAT | linux::O_TRUNC,
+        0o644,
+    );
+    if fd < 0 {
+        log!("Can't create the output file\n");
+        return;
+    }
+    for part in parts {
+        linux::write(fd, part.as_ptr(), part.len());
+    }
+    linux::close(fd);
+}
+
+// Writes tightly packed RGBA8 pixels as a binary PPM. PPM has no alpha, so
+// the pixels get packed down to RGB in place first.
+#[cfg(feature = "headless")]
+pub unsafe fn write_ppm(file_name: &str, width: u32, height: u32, pixels: &mut [u8]) {
+    let mut header = [0u8; 32];
+    let mut length = 0;
+    length += copy_text(&mut header[length..], b"P6\n");
+    length += u32_to_text(&mut header[length..], width);
+    length += copy_text(&mut header[length..], b" ");
+    length += u32_to_text(&mut header[length..], height);
+    length += copy_text(&mut header[length..], b"\n255\n");
+
+    let count = width as usize * height as usize;
+    let mut i = 0;
+    while i < count * 3 {
+        pixels[i] = pixels[i / 3 * 4 + i % 3];
+        i += 1;
+    }
+    write_file(file_name, &[&header[..length], &pixels[..count * 3]]);
+}
+
+#[cfg(feature = "headless")]
+fn copy_text(dest: &mut [u8], text: &[u8]) -> usize {
+    dest[..text.len()].copy_from_slice(text);
+    text.len()
+}
+
+#[cfg(feature = "headless")]
+fn u32_to_text(dest: &mut [u8], value: u32) -> usize {
+    let mut digits = [0u8; 10];
+    let mut count = 0;
+    let mut value = value;
+    loop {
+        digits[count] = b'0' + (value % 10) as u8;
+        value /= 10;
+        count += 1;
+        if value == 0 {
+            break;
+        }
+    }
+    let mut length = 0;
+    while count > 0 {
+        count -= 1;
+        dest[length] = digits[count];
+        length += 1;
+    }
+    length
+}
+
 #[cfg(feature = "logger")]
 pub fn get_c_string_length(buffer: &[u8]) -> usize {
     let mut buffer_text_len = 0;
@@ -71,6 +216,7 @@ pub fn get_c_string_length(buffer: &[u8]) -> usize {
 pub fn f32_to_text(dest: &mut [u8], value: f32, comma: bool) -> usize {
     let int_part = value as u32;
     let frac_part = ((value - int_part as f32) * 10000f32) as u32;
+    #[cfg(windows)]
     unsafe {
         winapi::um::winuser::wsprintfA(
             dest.as_mut_ptr() as *mut i8,
@@ -79,6 +225,35 @@ pub fn f32_to_text(dest: &mut [u8], value: f32, comma: bool) -> usize {
             frac_part,
         );
     }
+    // No wsprintfA without libc, so do "%d.%.4d" by hand.
+    #[cfg(target_os = "linux")]
+    {
+        let mut digits = [0u8; 10];
+        let mut count = 0;
+        let mut int_part = int_part;
+        loop {
+            digits[count] = b'0' + (int_part % 10) as u8;
+            int_part /= 10;
+            count += 1;
+            if int_part == 0 {
+                break;
+            }
+        }
+        let mut length = 0;
+        while count > 0 {
+            count -= 1;
+            dest[length] = digits[count];
+            length += 1;
+        }
+        dest[length] = b'.';
+        let mut divisor = 1000;
+        while divisor > 0 {
+            length += 1;
+            dest[length] = b'0' + (frac_part / divisor % 10) as u8;
+            divisor /= 10;
+        }
+        dest[length + 1] = 0;
+    }
     if comma {
         let length = get_c_string_length(dest);
         dest[length] = ',' as u8;
@@ -121,29 +296,122 @@ pub unsafe fn log3(message: &str, value1: f32, value2: f32, value3: f32) {
     log0(core::str::from_utf8_unchecked(&buffer[0..buffer_text_len]));
 }
 
+// Success codes, INCOMPLETE and SUBOPTIMAL_KHR included, are all positive.
 #[cfg(feature = "logger")]
-pub unsafe fn read_file(file_name: &str, dst: &mut [u8]) {
-    //let name = "dbg_out.txt\0";
-    let mut out = 0;
-
-    log!("Creating file for reading\n");
-    let h_file = CreateFileA(
-        file_name.as_ptr() as *const i8,
-        GENERIC_READ,
-        0,
-        0 as *mut winapi::um::minwinbase::SECURITY_ATTRIBUTES,
-        OPEN_EXISTING,
-        FILE_ATTRIBUTE_NORMAL,
-        0 as *mut winapi::ctypes::c_void,
-    );
-    log!("Reading...\n");
-    ReadFile(
-        h_file,
-        dst.as_mut_ptr() as *mut winapi::ctypes::c_void,
-        dst.len() as u32,
-        &mut out,
-        0 as *mut winapi::um::minwinbase::OVERLAPPED,
+pub unsafe fn check_result(
+    result: vk::Result,
+    function: &str,
+    allowed: &[vk::Result],
+) -> vk::Result {
+    if (result.as_raw() as i32) >= 0 || allowed.contains(&result) {
+        return result;
+    }
+    let mut buffer = [0u8; 128];
+    let length = join(
+        &mut buffer,
+        &[function, " failed: ", result_name(result), "\n"],
     );
-    log!("Close handle...\n");
-    CloseHandle(h_file);
+    log0(core::str::from_utf8_unchecked(&buffer[..length]));
+    #[cfg(windows)]
+    {
+        buffer[length - 1] = 0;
+        crate::miniwin::show_error(buffer.as_ptr() as *const i8);
+    }
+    Native::exit(1)
+}
+
+// Logs the parts as one line, for messages built from several strings.
+#[cfg(feature = "logger")]
+pub unsafe fn log_parts(parts: &[&str]) {
+    let mut buffer = [0u8; 128];
+    let length = join(&mut buffer, parts);
+    log0(core::str::from_utf8_unchecked(&buffer[..length]));
+}
+
+// Copies as much of the parts as fits and returns the length.
+#[cfg(feature = "logger")]
+fn join(buffer: &mut [u8], parts: &[&str]) -> usize {
+    let mut length = 0;
+    for part in parts {
+        let count = part.len().min(buffer.len() - length);
+        buffer[length..length + count].copy_from_slice(&part.as_bytes()[..count]);
+        length += count;
+    }
+    length
+}
+
+// Writes the panic message, which includes the name of any stub that got
+// called, to dbg_out.txt and exits instead of spinning.
+#[cfg(feature = "logger")]
+pub fn report_panic(info: &PanicInfo) -> ! {
+    let mut message = Message {
+        buffer: [0u8; 256],
+        length: 0,
+    };
+    let _ = write!(message, "{}", info);
+    message.buffer[message.length] = b'\n';
+    let length = message.length + 1;
+    unsafe {
+        log0(core::str::from_utf8_unchecked(&message.buffer[..length]));
+        #[cfg(windows)]
+        {
+            message.buffer[length - 1] = 0;
+            crate::miniwin::show_error(message.buffer.as_ptr() as *const i8);
+        }
+    }
+    Native::exit(1)
+}
+
+// Fixed-size core::fmt target. Anything past the end is dropped, keeping the
+// last byte free for the newline.
+#[cfg(feature = "logger")]
+struct Message {
+    buffer: [u8; 256],
+    length: usize,
+}
+
+#[cfg(feature = "logger")]
+impl Write for Message {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        let space = self.buffer.len() - 1 - self.length;
+        let count = text.len().min(space);
+        self.buffer[self.length..self.length + count].copy_from_slice(&text.as_bytes()[..count]);
+        self.length += count;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "logger")]
+pub fn result_name(result: vk::Result) -> &'static str {
+    match result {
+        vk::SUCCESS => "SUCCESS",
+        vk::NOT_READY => "NOT_READY",
+        vk::TIMEOUT => "TIMEOUT",
+        vk::EVENT_SET => "EVENT_SET",
+        vk::EVENT_RESET => "EVENT_RESET",
+        vk::INCOMPLETE => "INCOMPLETE",
+        vk::ERROR_OUT_OF_HOST_MEMORY => "ERROR_OUT_OF_HOST_MEMORY",
+        vk::ERROR_OUT_OF_DEVICE_MEMORY => "ERROR_OUT_OF_DEVICE_MEMORY",
+        vk::ERROR_INITIALIZATION_FAILED => "ERROR_INITIALIZATION_FAILED",
+        vk::ERROR_DEVICE_LOST => "ERROR_DEVICE_LOST",
+        vk::ERROR_MEMORY_MAP_FAILED => "ERROR_MEMORY_MAP_FAILED",
+        vk::ERROR_LAYER_NOT_PRESENT => "ERROR_LAYER_NOT_PRESENT",
+        vk::ERROR_EXTENSION_NOT_PRESENT => "ERROR_EXTENSION_NOT_PRESENT",
+        vk::ERROR_FEATURE_NOT_PRESENT => "ERROR_FEATURE_NOT_PRESENT",
+        vk::ERROR_INCOMPATIBLE_DRIVER => "ERROR_INCOMPATIBLE_DRIVER",
+        vk::ERROR_TOO_MANY_OBJECTS => "ERROR_TOO_MANY_OBJECTS",
+        vk::ERROR_FORMAT_NOT_SUPPORTED => "ERROR_FORMAT_NOT_SUPPORTED",
+        vk::ERROR_SURFACE_LOST_KHR => "ERROR_SURFACE_LOST_KHR",
+        vk::ERROR_NATIVE_WINDOW_IN_USE_KHR => "ERROR_NATIVE_WINDOW_IN_USE_KHR",
+        vk::SUBOPTIMAL_KHR => "SUBOPTIMAL_KHR",
+        vk::ERROR_OUT_OF_DATE_KHR => "ERROR_OUT_OF_DATE_KHR",
+        vk::ERROR_INCOMPATIBLE_DISPLAY_KHR => "ERROR_INCOMPATIBLE_DISPLAY_KHR",
+        vk::ERROR_VALIDATION_FAILED_EXT => "ERROR_VALIDATION_FAILED_EXT",
+        vk::ERROR_INVALID_SHADER_NV => "ERROR_INVALID_SHADER_NV",
+        vk::ERROR_OUT_OF_POOL_MEMORY_KHR => "ERROR_OUT_OF_POOL_MEMORY_KHR",
+        vk::ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT => {
+            "ERROR_FULL_SCREEN_EXCLUSIVE_MODE_LOST_EXT"
+        }
+        _ => "unknown VkResult",
+    }
 }
@@ -0,0 +1,382 @@
+// Hand-written half of the bindings: the macros that turn the generated
+// handle, enum, bitmask and command lists into types and tables of function
+// pointers, and the code that loads them.
+
+use core::ffi::c_void;
+#[cfg(not(all(windows, feature = "hash_imports", not(feature = "logger"))))]
+use core::mem;
+use core::ptr;
+#[cfg(not(all(windows, feature = "hash_imports", not(feature = "logger"))))]
+use cty::c_char;
+#[cfg(windows)]
+use winapi::shared::minwindef::HMODULE;
+#[cfg(all(windows, feature = "hash_imports", not(feature = "logger")))]
+use winapi::um::libloaderapi::GetModuleHandleA;
+#[cfg(all(windows, any(not(feature = "hash_imports"), feature = "logger")))]
+use winapi::um::libloaderapi::GetProcAddress;
+#[cfg(windows)]
+use winapi::um::libloaderapi::LoadLibraryA;
+
+use super::{Device, DevicePtrs, EntryPtrs, Instance, InstancePtrs};
+
+#[cfg(unix)]
+const RTLD_NOW: i32 = 2;
+
+// dlopen moved from libdl into libc with glibc 2.34; link both so either works.
+#[cfg(unix)]
+#[link(name = "dl")]
+#[link(name = "c")]
+extern "C" {
+    fn dlopen(filename: *const c_char, flags: i32) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *const c_void;
+}
+
+// Non-dispatchable handles are a u64 on every platform. Each one still gets a
+// type of its own, so passing a Framebuffer where a RenderPass belongs doesn't
+// compile.
+macro_rules! handle {
+    ($name:ident) => {
+        #[repr(transparent)]
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        pub struct $name(u64);
+
+        impl $name {
+            pub const NULL: $name = $name(0);
+        }
+    };
+}
+
+// Vulkan enums are open-ended: a newer driver can hand back values this file
+// has never heard of, which a Rust enum can't hold. So each one wraps a u32,
+// with its values as the constants that follow it.
+macro_rules! enumeration {
+    ($name:ident) => {
+        #[repr(transparent)]
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        pub struct $name(u32);
+
+        impl $name {
+            pub const fn from_raw(raw: u32) -> $name {
+                $name(raw)
+            }
+
+            pub const fn as_raw(self) -> u32 {
+                self.0
+            }
+        }
+    };
+}
+
+// Flag bits share their mask's type, so they combine with | into it directly.
+// Operators can't be called in a const, which is what union is for.
+macro_rules! bitmask {
+    ($name:ident, $base:ty) => {
+        #[repr(transparent)]
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        pub struct $name($base);
+
+        impl $name {
+            pub const EMPTY: $name = $name(0);
+
+            pub const fn from_raw(raw: $base) -> $name {
+                $name(raw)
+            }
+
+            pub const fn as_raw(self) -> $base {
+                self.0
+            }
+
+            pub const fn union(self, other: $name) -> $name {
+                $name(self.0 | other.0)
+            }
+
+            pub const fn contains(self, other: $name) -> bool {
+                self.0 & other.0 == other.0
+            }
+        }
+
+        impl core::ops::BitOr for $name {
+            type Output = $name;
+
+            fn bitor(self, other: $name) -> $name {
+                self.union(other)
+            }
+        }
+
+        impl core::ops::BitAnd for $name {
+            type Output = $name;
+
+            fn bitand(self, other: $name) -> $name {
+                $name(self.0 & other.0)
+            }
+        }
+    };
+}
+
+macro_rules! ptrs {
+    ($struct_name:ident, { $($name:ident => ($($param_n:ident: $param_ty:ty),*) -> $ret:ty,)+ }) => (
+        pub struct $struct_name {
+            $(
+                pub $name: extern "system" fn($($param_ty),*) -> $ret,
+            )+
+        }
+
+        impl core::fmt::Debug for $struct_name {
+            fn fmt(&self, fmt: &mut core::fmt::Formatter) -> ::core::result::Result<(), core::fmt::Error> {
+                let mut list = fmt.debug_struct(stringify!($struct_name));
+                self.for_each_function(|name, loaded| {
+                    list.field(name, &if loaded { "loaded" } else { "missing" });
+                });
+                list.finish()
+            }
+        }
+
+        unsafe impl Send for $struct_name {}
+        unsafe impl Sync for $struct_name {}
+
+        impl $struct_name {
+            // Every entry on its stub. Loading starts from here, and it's what
+            // for_each_function compares against to tell what was left behind.
+            fn unloaded() -> $struct_name {
+                $struct_name {
+                    $(
+                        $name: {
+                            #[cfg(not(all(windows, feature = "hash_imports", not(feature = "logger"))))]
+                            extern "system" fn $name($(_: $param_ty),*) -> $ret { panic!("function pointer `{}` not loaded", stringify!($name)) }
+                            #[cfg(all(windows, feature = "hash_imports", not(feature = "logger")))]
+                            extern "system" fn $name($(_: $param_ty),*) -> $ret { panic!() }
+                            $name
+                        },
+                    )+
+                }
+            }
+
+            #[cfg(not(all(windows, feature = "hash_imports", not(feature = "logger"))))]
+            pub fn load<F>(mut f: F) -> $struct_name
+                where F: FnMut(&*const i8) -> *const core::ffi::c_void
+                {
+                    let mut table = $struct_name::unloaded();
+                    $(
+                        let name = (concat!("vk", stringify!($name), "\0").as_bytes()).as_ptr() as *const i8;
+                        let val = f(&name);
+                        if !val.is_null() { table.$name = unsafe { core::mem::transmute(val) }; }
+                    )+
+                    table
+                }
+
+            // Same as load, but only a hash of each name makes it into the
+            // binary. The stubs stay anonymous for the same reason.
+            #[cfg(all(windows, feature = "hash_imports", not(feature = "logger")))]
+            pub fn load_by_hash<F>(mut f: F) -> $struct_name
+                where F: FnMut(u32) -> *const core::ffi::c_void
+                {
+                    let mut table = $struct_name::unloaded();
+                    $(
+                        let val = {
+                            const HASH: u32 = crate::vk::loader::name_hash(concat!("vk", stringify!($name)).as_bytes());
+                            f(HASH)
+                        };
+                        if !val.is_null() { table.$name = unsafe { core::mem::transmute(val) }; }
+                    )+
+                    table
+                }
+
+            // Calls `f` with the name of every entry, minus the "vk", and
+            // whether it resolved or is still on its stub.
+            pub fn for_each_function<F>(&self, mut f: F)
+                where F: FnMut(&'static str, bool)
+                {
+                    let unloaded = $struct_name::unloaded();
+                    $(
+                        f(stringify!($name), self.$name as usize != unloaded.$name as usize);
+                    )+
+                }
+
+            $(
+                #[inline]
+                pub unsafe fn $name(&self $(, $param_n: $param_ty)*) -> $ret {
+                    let ptr = self.$name;
+                    ptr($($param_n),*)
+                }
+            )+
+        }
+    )
+}
+
+#[cfg(not(all(windows, feature = "hash_imports", not(feature = "logger"))))]
+type GetInstanceProcAddr = extern "system" fn(Instance, *const c_char) -> *const c_void;
+
+// Only vkGetInstanceProcAddr comes from the loader library itself. Everything
+// else is looked up through it, or through vkGetDeviceProcAddr once there is a
+// device, so device calls skip the loader's trampolines.
+#[cfg(all(windows, any(not(feature = "hash_imports"), feature = "logger")))]
+pub fn init() -> EntryPtrs {
+    unsafe {
+        let handle: HMODULE = LoadLibraryA("vulkan-1.dll\0".as_ptr() as *const i8);
+        let get_instance_proc_addr =
+            GetProcAddress(handle, "vkGetInstanceProcAddr\0".as_ptr() as *const i8);
+        load_entry(get_instance_proc_addr as *const c_void)
+    }
+}
+
+#[cfg(unix)]
+pub fn init() -> EntryPtrs {
+    unsafe {
+        let handle = dlopen("libvulkan.so.1\0".as_ptr() as *const c_char, RTLD_NOW);
+        if handle.is_null() {
+            return load_entry(ptr::null());
+        }
+        load_entry(dlsym(
+            handle,
+            "vkGetInstanceProcAddr\0".as_ptr() as *const c_char,
+        ))
+    }
+}
+
+// Global commands are the ones vkGetInstanceProcAddr answers without an
+// instance. Before Vulkan 1.2 that doesn't include itself, so keep the pointer
+// the library gave us.
+#[cfg(not(all(windows, feature = "hash_imports", not(feature = "logger"))))]
+fn load_entry(get_instance_proc_addr: *const c_void) -> EntryPtrs {
+    // No loader installed. Leave every entry on its panicking stub.
+    if get_instance_proc_addr.is_null() {
+        unsafe {
+            crate::log!("No Vulkan loader found, every function is on its stub\n");
+        }
+        return EntryPtrs::load(|_| ptr::null());
+    }
+    let get_instance_proc_addr: GetInstanceProcAddr =
+        unsafe { mem::transmute(get_instance_proc_addr) };
+    let mut entry = EntryPtrs::load(|name| get_instance_proc_addr(0, *name));
+    entry.GetInstanceProcAddr = get_instance_proc_addr;
+    #[cfg(feature = "logger")]
+    entry.for_each_function(|name, loaded| report_missing("entry", name, loaded));
+    entry
+}
+
+#[cfg(not(all(windows, feature = "hash_imports", not(feature = "logger"))))]
+pub fn load_instance(entry: &EntryPtrs, instance: Instance) -> InstancePtrs {
+    let instance = InstancePtrs::load(|name| unsafe { entry.GetInstanceProcAddr(instance, *name) });
+    #[cfg(feature = "logger")]
+    instance.for_each_function(|name, loaded| report_missing("instance", name, loaded));
+    instance
+}
+
+#[cfg(not(all(windows, feature = "hash_imports", not(feature = "logger"))))]
+pub fn load_device(instance: &InstancePtrs, device: Device) -> DevicePtrs {
+    let device = DevicePtrs::load(|name| unsafe { instance.GetDeviceProcAddr(device, *name) });
+    #[cfg(feature = "logger")]
+    device.for_each_function(|name, loaded| report_missing("device", name, loaded));
+    device
+}
+
+// Not everything in the tables is meant to resolve: extensions that weren't
+// enabled and other platforms' surface calls come back null too. Listing them
+// up front means a stub that does get called has already been named in
+// dbg_out.txt.
+#[cfg(feature = "logger")]
+fn report_missing(table: &str, name: &str, loaded: bool) {
+    if !loaded {
+        unsafe {
+            crate::util::log_parts(&["vk", name, " not loaded into the ", table, " table\n"])
+        };
+    }
+}
+
+// Size builds skip the strings entirely: every table, device level included, is
+// filled from vulkan-1.dll's own exports by matching name hashes. That means
+// device calls go through the loader's trampolines again, and extension
+// commands the loader doesn't export stay on their stubs. Logger builds keep
+// the strings so a missing function can still be named.
+#[cfg(all(windows, feature = "hash_imports", not(feature = "logger")))]
+const LIBRARY: &str = "vulkan-1.dll\0";
+
+#[cfg(all(windows, feature = "hash_imports", not(feature = "logger")))]
+pub fn init() -> EntryPtrs {
+    unsafe {
+        let module = LoadLibraryA(LIBRARY.as_ptr() as *const i8);
+        EntryPtrs::load_by_hash(|hash| find_export(module, hash))
+    }
+}
+
+#[cfg(all(windows, feature = "hash_imports", not(feature = "logger")))]
+pub fn load_instance(_entry: &EntryPtrs, _instance: Instance) -> InstancePtrs {
+    unsafe {
+        let module = GetModuleHandleA(LIBRARY.as_ptr() as *const i8);
+        InstancePtrs::load_by_hash(|hash| find_export(module, hash))
+    }
+}
+
+#[cfg(all(windows, feature = "hash_imports", not(feature = "logger")))]
+pub fn load_device(_instance: &InstancePtrs, _device: Device) -> DevicePtrs {
+    unsafe {
+        let module = GetModuleHandleA(LIBRARY.as_ptr() as *const i8);
+        DevicePtrs::load_by_hash(|hash| find_export(module, hash))
+    }
+}
+
+// FNV-1a, small enough to run at compile time for every table entry.
+#[cfg(all(windows, feature = "hash_imports", not(feature = "logger")))]
+const FNV_OFFSET_BASIS: u32 = 0x811c9dc5;
+
+#[cfg(all(windows, feature = "hash_imports", not(feature = "logger")))]
+const fn hash_byte(hash: u32, byte: u8) -> u32 {
+    (hash ^ byte as u32).wrapping_mul(0x01000193)
+}
+
+#[cfg(all(windows, feature = "hash_imports", not(feature = "logger")))]
+pub const fn name_hash(name: &[u8]) -> u32 {
+    let mut hash = FNV_OFFSET_BASIS;
+    let mut i = 0;
+    while i < name.len() {
+        hash = hash_byte(hash, name[i]);
+        i += 1;
+    }
+    hash
+}
+
+// Offsets into the PE headers. The export directory is the first data
+// directory entry, which moves with the size of the optional header.
+#[cfg(all(windows, feature = "hash_imports", not(feature = "logger")))]
+const PE_HEADER_OFFSET: usize = 0x3c;
+#[cfg(all(
+    all(windows, feature = "hash_imports", not(feature = "logger")),
+    target_pointer_width = "64"
+))]
+const EXPORT_DIRECTORY_OFFSET: usize = 0x88;
+#[cfg(all(
+    all(windows, feature = "hash_imports", not(feature = "logger")),
+    target_pointer_width = "32"
+))]
+const EXPORT_DIRECTORY_OFFSET: usize = 0x78;
+
+// Walks the module's export name table and returns the function whose name
+// hashes to `hash`, or null. Same lookup GetProcAddress does, minus the string.
+#[cfg(all(windows, feature = "hash_imports", not(feature = "logger")))]
+unsafe fn find_export(module: HMODULE, hash: u32) -> *const c_void {
+    if module.is_null() {
+        return ptr::null();
+    }
+    let base = module as *const u8;
+    let read_u32 = |offset: usize| *(base.add(offset) as *const u32) as usize;
+    let pe_header = read_u32(PE_HEADER_OFFSET);
+    let exports = read_u32(pe_header + EXPORT_DIRECTORY_OFFSET);
+    let name_count = read_u32(exports + 0x18);
+    let functions = read_u32(exports + 0x1c);
+    let names = read_u32(exports + 0x20);
+    let ordinals = read_u32(exports + 0x24);
+    for i in 0..name_count {
+        // Hashed as it's walked, so there's no strlen to link against.
+        let mut name = base.add(read_u32(names + i * 4));
+        let mut name_hash = FNV_OFFSET_BASIS;
+        while *name != 0 {
+            name_hash = hash_byte(name_hash, *name);
+            name = name.add(1);
+        }
+        if name_hash == hash {
+            let ordinal = *(base.add(ordinals + i * 2) as *const u16) as usize;
+            return base.add(read_u32(functions + ordinal * 4)) as *const c_void;
+        }
+    }
+    ptr::null()
+}
@@ -0,0 +1,696 @@
+#![allow(non_camel_case_types)]
+#![allow(non_upper_case_globals)]
+
+use core::ffi::c_void;
+use core::mem::MaybeUninit;
+use core::ptr;
+
+use cty::{c_char, c_int};
+
+use crate::linux;
+use crate::log;
+use crate::platform::{Key, Platform, Surface, REQUESTED_SIZE};
+use crate::vk::{
+    Instance, InstancePtrs, SurfaceKHR, WaylandSurfaceCreateFlagsKHR, WaylandSurfaceCreateInfoKHR,
+    STRUCTURE_TYPE_WAYLAND_SURFACE_CREATE_INFO_KHR,
+};
+use crate::vk_check;
+
+pub enum wl_display {}
+pub enum wl_proxy {}
+
+#[repr(C)]
+pub struct wl_message {
+    pub name: *const c_char,
+    pub signature: *const c_char,
+    pub types: *const Option<&'static wl_interface>,
+}
+
+#[repr(C)]
+pub struct wl_interface {
+    pub name: *const c_char,
+    pub version: c_int,
+    pub method_count: c_int,
+    pub methods: *const wl_message,
+    pub event_count: c_int,
+    pub events: *const wl_message,
+}
+
+unsafe impl Sync for wl_message {}
+unsafe impl Sync for wl_interface {}
+
+#[link(name = "wayland-client")]
+extern "C" {
+    static wl_registry_interface: wl_interface;
+    static wl_compositor_interface: wl_interface;
+    static wl_surface_interface: wl_interface;
+    static wl_seat_interface: wl_interface;
+    static wl_keyboard_interface: wl_interface;
+    static wl_pointer_interface: wl_interface;
+
+    fn wl_display_connect(name: *const c_char) -> *mut wl_display;
+    fn wl_display_roundtrip(display: *mut wl_display) -> c_int;
+    fn wl_display_dispatch_pending(display: *mut wl_display) -> c_int;
+    fn wl_display_flush(display: *mut wl_display) -> c_int;
+    fn wl_display_get_fd(display: *mut wl_display) -> c_int;
+    fn wl_display_prepare_read(display: *mut wl_display) -> c_int;
+    fn wl_display_read_events(display: *mut wl_display) -> c_int;
+    fn wl_display_cancel_read(display: *mut wl_display);
+    fn wl_proxy_add_listener(
+        proxy: *mut wl_proxy,
+        implementation: *const c_void,
+        data: *mut c_void,
+    ) -> c_int;
+    fn wl_proxy_marshal(proxy: *mut wl_proxy, opcode: u32, ...);
+    fn wl_proxy_marshal_constructor(
+        proxy: *mut wl_proxy,
+        opcode: u32,
+        interface: *const wl_interface,
+        ...
+    ) -> *mut wl_proxy;
+    fn wl_proxy_marshal_constructor_versioned(
+        proxy: *mut wl_proxy,
+        opcode: u32,
+        interface: *const wl_interface,
+        version: u32,
+        ...
+    ) -> *mut wl_proxy;
+}
+
+// xdg-shell is not part of libwayland-client, so its interface tables are
+// spelled out here the same way wayland-scanner would generate them. Only the
+// types we actually construct are filled in.
+macro_rules! message {
+    ($name:expr, $signature:expr, $types:expr) => {
+        wl_message {
+            name: concat!($name, "\0").as_ptr() as *const c_char,
+            signature: concat!($signature, "\0").as_ptr() as *const c_char,
+            types: $types.as_ptr(),
+        }
+    };
+}
+
+static NO_TYPES: [Option<&wl_interface>; 4] = [None, None, None, None];
+static XDG_SURFACE_TYPES: [Option<&wl_interface>; 2] = [Some(&xdg_surface_interface), None];
+static XDG_TOPLEVEL_TYPES: [Option<&wl_interface>; 1] = [Some(&xdg_toplevel_interface)];
+
+static XDG_WM_BASE_REQUESTS: [wl_message; 4] = [
+    message!("destroy", "", NO_TYPES),
+    message!("create_positioner", "n", NO_TYPES),
+    message!("get_xdg_surface", "no", XDG_SURFACE_TYPES),
+    message!("pong", "u", NO_TYPES),
+];
+static XDG_WM_BASE_EVENTS: [wl_message; 1] = [message!("ping", "u", NO_TYPES)];
+
+pub static xdg_wm_base_interface: wl_interface = wl_interface {
+    name: "xdg_wm_base\0".as_ptr() as *const c_char,
+    version: 1,
+    method_count: XDG_WM_BASE_REQUESTS.len() as c_int,
+    methods: XDG_WM_BASE_REQUESTS.as_ptr(),
+    event_count: XDG_WM_BASE_EVENTS.len() as c_int,
+    events: XDG_WM_BASE_EVENTS.as_ptr(),
+};
+
+static XDG_SURFACE_REQUESTS: [wl_message; 5] = [
+    message!("destroy", "", NO_TYPES),
+    message!("get_toplevel", "n", XDG_TOPLEVEL_TYPES),
+    message!("get_popup", "n?oo", NO_TYPES),
+    message!("set_window_geometry", "iiii", NO_TYPES),
+    message!("ack_configure", "u", NO_TYPES),
+];
+static XDG_SURFACE_EVENTS: [wl_message; 1] = [message!("configure", "u", NO_TYPES)];
+
+pub static xdg_surface_interface: wl_interface = wl_interface {
+    name: "xdg_surface\0".as_ptr() as *const c_char,
+    version: 1,
+    method_count: XDG_SURFACE_REQUESTS.len() as c_int,
+    methods: XDG_SURFACE_REQUESTS.as_ptr(),
+    event_count: XDG_SURFACE_EVENTS.len() as c_int,
+    events: XDG_SURFACE_EVENTS.as_ptr(),
+};
+
+static XDG_TOPLEVEL_REQUESTS: [wl_message; 14] = [
+    message!("destroy", "", NO_TYPES),
+    message!("set_parent", "?o", NO_TYPES),
+    message!("set_title", "s", NO_TYPES),
+    message!("set_app_id", "s", NO_TYPES),
+    message!("show_window_menu", "ouii", NO_TYPES),
+    message!("move", "ou", NO_TYPES),
+    message!("resize", "ouu", NO_TYPES),
+    message!("set_max_size", "ii", NO_TYPES),
+    message!("set_min_size", "ii", NO_TYPES),
+    message!("set_maximized", "", NO_TYPES),
+    message!("unset_maximized", "", NO_TYPES),
+    message!("set_fullscreen", "?o", NO_TYPES),
+    message!("unset_fullscreen", "", NO_TYPES),
+    message!("set_minimized", "", NO_TYPES),
+];
+static XDG_TOPLEVEL_EVENTS: [wl_message; 2] = [
+    message!("configure", "iia", NO_TYPES),
+    message!("close", "", NO_TYPES),
+];
+
+pub static xdg_toplevel_interface: wl_interface = wl_interface {
+    name: "xdg_toplevel\0".as_ptr() as *const c_char,
+    version: 1,
+    method_count: XDG_TOPLEVEL_REQUESTS.len() as c_int,
+    methods: XDG_TOPLEVEL_REQUESTS.as_ptr(),
+    event_count: XDG_TOPLEVEL_EVENTS.len() as c_int,
+    events: XDG_TOPLEVEL_EVENTS.as_ptr(),
+};
+
+// Request opcodes, in protocol order.
+const WL_DISPLAY_GET_REGISTRY: u32 = 1;
+const WL_REGISTRY_BIND: u32 = 0;
+const WL_COMPOSITOR_CREATE_SURFACE: u32 = 0;
+const WL_SURFACE_COMMIT: u32 = 6;
+const WL_SEAT_GET_POINTER: u32 = 0;
+const WL_SEAT_GET_KEYBOARD: u32 = 1;
+const XDG_WM_BASE_GET_XDG_SURFACE: u32 = 2;
+const XDG_WM_BASE_PONG: u32 = 3;
+const XDG_SURFACE_GET_TOPLEVEL: u32 = 1;
+const XDG_SURFACE_ACK_CONFIGURE: u32 = 4;
+const XDG_TOPLEVEL_SET_TITLE: u32 = 2;
+#[cfg(feature = "fullscreen")]
+const XDG_TOPLEVEL_SET_FULLSCREEN: u32 = 11;
+
+const WL_SEAT_CAPABILITY_POINTER: u32 = 1;
+const WL_SEAT_CAPABILITY_KEYBOARD: u32 = 2;
+const WL_KEYBOARD_KEY_STATE_PRESSED: u32 = 1;
+const WL_POINTER_BUTTON_STATE_PRESSED: u32 = 1;
+// Linux evdev codes, not XKB keysyms.
+const KEY_ESC: u32 = 1;
+const BTN_LEFT: u32 = 0x110;
+
+#[repr(C)]
+struct RegistryListener {
+    global: extern "C" fn(*mut c_void, *mut wl_proxy, u32, *const c_char, u32),
+    global_remove: extern "C" fn(*mut c_void, *mut wl_proxy, u32),
+}
+
+#[repr(C)]
+struct WmBaseListener {
+    ping: extern "C" fn(*mut c_void, *mut wl_proxy, u32),
+}
+
+#[repr(C)]
+struct XdgSurfaceListener {
+    configure: extern "C" fn(*mut c_void, *mut wl_proxy, u32),
+}
+
+#[repr(C)]
+struct XdgToplevelListener {
+    configure: extern "C" fn(*mut c_void, *mut wl_proxy, i32, i32, *mut c_void),
+    close: extern "C" fn(*mut c_void, *mut wl_proxy),
+}
+
+#[repr(C)]
+struct SeatListener {
+    capabilities: extern "C" fn(*mut c_void, *mut wl_proxy, u32),
+}
+
+#[repr(C)]
+struct KeyboardListener {
+    keymap: extern "C" fn(*mut c_void, *mut wl_proxy, u32, c_int, u32),
+    enter: extern "C" fn(*mut c_void, *mut wl_proxy, u32, *mut wl_proxy, *mut c_void),
+    leave: extern "C" fn(*mut c_void, *mut wl_proxy, u32, *mut wl_proxy),
+    key: extern "C" fn(*mut c_void, *mut wl_proxy, u32, u32, u32, u32),
+    modifiers: extern "C" fn(*mut c_void, *mut wl_proxy, u32, u32, u32, u32, u32),
+}
+
+// Positions are wl_fixed_t, 24.8 fixed point.
+#[repr(C)]
+struct PointerListener {
+    enter: extern "C" fn(*mut c_void, *mut wl_proxy, u32, *mut wl_proxy, i32, i32),
+    leave: extern "C" fn(*mut c_void, *mut wl_proxy, u32, *mut wl_proxy),
+    motion: extern "C" fn(*mut c_void, *mut wl_proxy, u32, i32, i32),
+    button: extern "C" fn(*mut c_void, *mut wl_proxy, u32, u32, u32, u32),
+    axis: extern "C" fn(*mut c_void, *mut wl_proxy, u32, u32, i32),
+}
+
+static mut COMPOSITOR: *mut wl_proxy = ptr::null_mut();
+static mut WM_BASE: *mut wl_proxy = ptr::null_mut();
+static mut SEAT: *mut wl_proxy = ptr::null_mut();
+static mut CONFIGURED: bool = false;
+static mut RUNNING: bool = true;
+static mut ESCAPE: bool = false;
+static mut MOUSE_LEFT: bool = false;
+static mut MOUSE_X: i32 = 0;
+static mut MOUSE_Y: i32 = 0;
+static mut WIDTH: u32 = REQUESTED_SIZE.0;
+static mut HEIGHT: u32 = REQUESTED_SIZE.1;
+
+unsafe fn strcmp(a: *const c_char, b: &str) -> bool {
+    let b = b.as_bytes();
+    let mut i = 0;
+    while i < b.len() {
+        if *a.add(i) as u8 != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    *a.add(i) == 0
+}
+
+unsafe fn bind(registry: *mut wl_proxy, name: u32, interface: &wl_interface) -> *mut wl_proxy {
+    wl_proxy_marshal_constructor_versioned(
+        registry,
+        WL_REGISTRY_BIND,
+        interface,
+        1,
+        name,
+        interface.name,
+        1u32,
+        ptr::null_mut::<c_void>(),
+    )
+}
+
+extern "C" fn registry_global(
+    _data: *mut c_void,
+    registry: *mut wl_proxy,
+    name: u32,
+    interface: *const c_char,
+    _version: u32,
+) {
+    unsafe {
+        if strcmp(interface, "wl_compositor") {
+            COMPOSITOR = bind(registry, name, &wl_compositor_interface);
+        } else if strcmp(interface, "xdg_wm_base") {
+            WM_BASE = bind(registry, name, &xdg_wm_base_interface);
+        } else if strcmp(interface, "wl_seat") {
+            SEAT = bind(registry, name, &wl_seat_interface);
+        }
+    }
+}
+
+extern "C" fn registry_global_remove(_data: *mut c_void, _registry: *mut wl_proxy, _name: u32) {}
+
+static REGISTRY_LISTENER: RegistryListener = RegistryListener {
+    global: registry_global,
+    global_remove: registry_global_remove,
+};
+
+extern "C" fn wm_base_ping(_data: *mut c_void, wm_base: *mut wl_proxy, serial: u32) {
+    unsafe { wl_proxy_marshal(wm_base, XDG_WM_BASE_PONG, serial) }
+}
+
+static WM_BASE_LISTENER: WmBaseListener = WmBaseListener { ping: wm_base_ping };
+
+extern "C" fn xdg_surface_configure(_data: *mut c_void, xdg_surface: *mut wl_proxy, serial: u32) {
+    unsafe {
+        wl_proxy_marshal(xdg_surface, XDG_SURFACE_ACK_CONFIGURE, serial);
+        CONFIGURED = true;
+    }
+}
+
+static XDG_SURFACE_LISTENER: XdgSurfaceListener = XdgSurfaceListener {
+    configure: xdg_surface_configure,
+};
+
+extern "C" fn toplevel_configure(
+    _data: *mut c_void,
+    _toplevel: *mut wl_proxy,
+    width: i32,
+    height: i32,
+    _states: *mut c_void,
+) {
+    // Zero means the compositor leaves the size up to us, so keep ours.
+    if width > 0 && height > 0 {
+        unsafe {
+            WIDTH = width as u32;
+            HEIGHT = height as u32;
+        }
+    }
+}
+
+extern "C" fn toplevel_close(_data: *mut c_void, _toplevel: *mut wl_proxy) {
+    unsafe { RUNNING = false }
+}
+
+static XDG_TOPLEVEL_LISTENER: XdgToplevelListener = XdgToplevelListener {
+    configure: toplevel_configure,
+    close: toplevel_close,
+};
+
+extern "C" fn keyboard_keymap(
+    _data: *mut c_void,
+    _keyboard: *mut wl_proxy,
+    _format: u32,
+    fd: c_int,
+    _size: u32,
+) {
+    // We only look at raw evdev codes, so the keymap is never mapped.
+    unsafe {
+        linux::close(fd);
+    }
+}
+
+extern "C" fn keyboard_enter(
+    _data: *mut c_void,
+    _keyboard: *mut wl_proxy,
+    _serial: u32,
+    _surface: *mut wl_proxy,
+    _keys: *mut c_void,
+) {
+}
+
+extern "C" fn keyboard_leave(
+    _data: *mut c_void,
+    _keyboard: *mut wl_proxy,
+    _serial: u32,
+    _surface: *mut wl_proxy,
+) {
+}
+
+extern "C" fn keyboard_key(
+    _data: *mut c_void,
+    _keyboard: *mut wl_proxy,
+    _serial: u32,
+    _time: u32,
+    key: u32,
+    state: u32,
+) {
+    if key == KEY_ESC {
+        unsafe { ESCAPE = state == WL_KEYBOARD_KEY_STATE_PRESSED }
+    }
+}
+
+extern "C" fn keyboard_modifiers(
+    _data: *mut c_void,
+    _keyboard: *mut wl_proxy,
+    _serial: u32,
+    _depressed: u32,
+    _latched: u32,
+    _locked: u32,
+    _group: u32,
+) {
+}
+
+static KEYBOARD_LISTENER: KeyboardListener = KeyboardListener {
+    keymap: keyboard_keymap,
+    enter: keyboard_enter,
+    leave: keyboard_leave,
+    key: keyboard_key,
+    modifiers: keyboard_modifiers,
+};
+
+extern "C" fn pointer_enter(
+    _data: *mut c_void,
+    _pointer: *mut wl_proxy,
+    _serial: u32,
+    _surface: *mut wl_proxy,
+    x: i32,
+    y: i32,
+) {
+    unsafe {
+        MOUSE_X = x >> 8;
+        MOUSE_Y = y >> 8;
+    }
+}
+
+extern "C" fn pointer_leave(
+    _data: *mut c_void,
+    _pointer: *mut wl_proxy,
+    _serial: u32,
+    _surface: *mut wl_proxy,
+) {
+    // The release happens outside our surface, so we never see it.
+    unsafe { MOUSE_LEFT = false }
+}
+
+extern "C" fn pointer_motion(
+    _data: *mut c_void,
+    _pointer: *mut wl_proxy,
+    _time: u32,
+    x: i32,
+    y: i32,
+) {
+    unsafe {
+        MOUSE_X = x >> 8;
+        MOUSE_Y = y >> 8;
+    }
+}
+
+extern "C" fn pointer_button(
+    _data: *mut c_void,
+    _pointer: *mut wl_proxy,
+    _serial: u32,
+    _time: u32,
+    button: u32,
+    state: u32,
+) {
+    if button == BTN_LEFT {
+        unsafe { MOUSE_LEFT = state == WL_POINTER_BUTTON_STATE_PRESSED }
+    }
+}
+
+extern "C" fn pointer_axis(
+    _data: *mut c_void,
+    _pointer: *mut wl_proxy,
+    _time: u32,
+    _axis: u32,
+    _value: i32,
+) {
+}
+
+static POINTER_LISTENER: PointerListener = PointerListener {
+    enter: pointer_enter,
+    leave: pointer_leave,
+    motion: pointer_motion,
+    button: pointer_button,
+    axis: pointer_axis,
+};
+
+extern "C" fn seat_capabilities(_data: *mut c_void, seat: *mut wl_proxy, capabilities: u32) {
+    if capabilities & WL_SEAT_CAPABILITY_KEYBOARD != 0 {
+        unsafe {
+            let keyboard = wl_proxy_marshal_constructor(
+                seat,
+                WL_SEAT_GET_KEYBOARD,
+                &wl_keyboard_interface,
+                ptr::null_mut::<c_void>(),
+            );
+            wl_proxy_add_listener(
+                keyboard,
+                &KEYBOARD_LISTENER as *const _ as *const c_void,
+                ptr::null_mut(),
+            );
+        }
+    }
+    if capabilities & WL_SEAT_CAPABILITY_POINTER != 0 {
+        unsafe {
+            let pointer = wl_proxy_marshal_constructor(
+                seat,
+                WL_SEAT_GET_POINTER,
+                &wl_pointer_interface,
+                ptr::null_mut::<c_void>(),
+            );
+            wl_proxy_add_listener(
+                pointer,
+                &POINTER_LISTENER as *const _ as *const c_void,
+                ptr::null_mut(),
+            );
+        }
+    }
+}
+
+static SEAT_LISTENER: SeatListener = SeatListener {
+    capabilities: seat_capabilities,
+};
+
+#[derive(Clone, Copy)]
+pub struct WlWindow {
+    pub display: *mut wl_display,
+    pub surface: *mut wl_proxy,
+}
+
+pub fn create_window() -> WlWindow {
+    unsafe {
+        let display = wl_display_connect(ptr::null());
+        if display.is_null() {
+            log!("Can't connect to the Wayland display, is WAYLAND_DISPLAY set?\n");
+            WlWindow::exit(1);
+        }
+        let registry = wl_proxy_marshal_constructor(
+            display as *mut wl_proxy,
+            WL_DISPLAY_GET_REGISTRY,
+            &wl_registry_interface,
+            ptr::null_mut::<c_void>(),
+        );
+        wl_proxy_add_listener(
+            registry,
+            &REGISTRY_LISTENER as *const _ as *const c_void,
+            ptr::null_mut(),
+        );
+        if wl_display_roundtrip(display) == -1 {
+            log!("Lost the Wayland connection while listing globals\n");
+            WlWindow::exit(1);
+        }
+        // Every one of these is needed to get a window with input, so a
+        // compositor that lacks one can't run us at all.
+        if COMPOSITOR.is_null() {
+            log!("The compositor doesn't offer wl_compositor\n");
+            WlWindow::exit(1);
+        }
+        if WM_BASE.is_null() {
+            log!("The compositor doesn't offer xdg_wm_base\n");
+            WlWindow::exit(1);
+        }
+        if SEAT.is_null() {
+            log!("The compositor doesn't offer wl_seat\n");
+            WlWindow::exit(1);
+        }
+
+        wl_proxy_add_listener(
+            WM_BASE,
+            &WM_BASE_LISTENER as *const _ as *const c_void,
+            ptr::null_mut(),
+        );
+        wl_proxy_add_listener(
+            SEAT,
+            &SEAT_LISTENER as *const _ as *const c_void,
+            ptr::null_mut(),
+        );
+
+        let surface = wl_proxy_marshal_constructor(
+            COMPOSITOR,
+            WL_COMPOSITOR_CREATE_SURFACE,
+            &wl_surface_interface,
+            ptr::null_mut::<c_void>(),
+        );
+        let xdg_surface = wl_proxy_marshal_constructor(
+            WM_BASE,
+            XDG_WM_BASE_GET_XDG_SURFACE,
+            &xdg_surface_interface,
+            ptr::null_mut::<c_void>(),
+            surface,
+        );
+        wl_proxy_add_listener(
+            xdg_surface,
+            &XDG_SURFACE_LISTENER as *const _ as *const c_void,
+            ptr::null_mut(),
+        );
+        let toplevel = wl_proxy_marshal_constructor(
+            xdg_surface,
+            XDG_SURFACE_GET_TOPLEVEL,
+            &xdg_toplevel_interface,
+            ptr::null_mut::<c_void>(),
+        );
+        wl_proxy_add_listener(
+            toplevel,
+            &XDG_TOPLEVEL_LISTENER as *const _ as *const c_void,
+            ptr::null_mut(),
+        );
+        wl_proxy_marshal(
+            toplevel,
+            XDG_TOPLEVEL_SET_TITLE,
+            "GLWIN\0".as_ptr() as *const c_char,
+        );
+
+        // A null output lets the compositor pick which screen to fill.
+        #[cfg(feature = "fullscreen")]
+        wl_proxy_marshal(
+            toplevel,
+            XDG_TOPLEVEL_SET_FULLSCREEN,
+            ptr::null_mut::<wl_proxy>(),
+        );
+
+        // The surface may not have a buffer attached (i.e. be presented to)
+        // until the first configure has been acknowledged.
+        wl_proxy_marshal(surface, WL_SURFACE_COMMIT);
+        while !CONFIGURED {
+            if wl_display_roundtrip(display) == -1 {
+                log!("Lost the Wayland connection before the window was configured\n");
+                WlWindow::exit(1);
+            }
+        }
+
+        WlWindow { display, surface }
+    }
+}
+
+// Dispatch whatever the compositor has sent, waiting up to timeout
+// milliseconds for it to send something, or forever if it is -1. Returns
+// false once the toplevel has been closed.
+pub fn handle_message(window: WlWindow, timeout: i32) -> bool {
+    unsafe {
+        let display = window.display;
+        while wl_display_prepare_read(display) != 0 {
+            wl_display_dispatch_pending(display);
+        }
+        wl_display_flush(display);
+
+        let mut fds = linux::PollFd {
+            fd: wl_display_get_fd(display),
+            events: linux::POLLIN,
+            revents: 0,
+        };
+        if linux::poll(&mut fds, 1, timeout) > 0 {
+            wl_display_read_events(display);
+        } else {
+            wl_display_cancel_read(display);
+        }
+        wl_display_dispatch_pending(display);
+
+        RUNNING
+    }
+}
+
+impl Platform for WlWindow {
+    const INSTANCE_EXTENSIONS: &'static [*const i8] = &[
+        "VK_KHR_surface\0".as_ptr() as *const i8,
+        "VK_KHR_wayland_surface\0".as_ptr() as *const i8,
+    ];
+
+    fn create_window() -> Self {
+        create_window()
+    }
+
+    fn pump_events(&mut self) -> bool {
+        handle_message(*self, 0)
+    }
+
+    // Dispatches whatever arrives too. A close is remembered in RUNNING for
+    // pump_events to report.
+    fn wait_events(&mut self) {
+        handle_message(*self, -1);
+    }
+
+    fn key_down(&self, key: Key) -> bool {
+        match key {
+            Key::Escape => unsafe { ESCAPE },
+            Key::MouseLeft => unsafe { MOUSE_LEFT },
+        }
+    }
+
+    fn mouse_position(&self) -> (i32, i32) {
+        unsafe { (MOUSE_X, MOUSE_Y) }
+    }
+
+    fn size(&self) -> (u32, u32) {
+        unsafe { (WIDTH, HEIGHT) }
+    }
+}
+
+impl Surface for WlWindow {
+    fn create_surface(&self, ips: &InstancePtrs, instance: Instance) -> SurfaceKHR {
+        let create_info = WaylandSurfaceCreateInfoKHR {
+            sType: STRUCTURE_TYPE_WAYLAND_SURFACE_CREATE_INFO_KHR,
+            display: self.display as *mut c_void,
+            surface: self.surface as *mut c_void,
+            pNext: ptr::null(),
+            flags: WaylandSurfaceCreateFlagsKHR::EMPTY,
+        };
+
+        let mut surface = MaybeUninit::uninit();
+        unsafe {
+            vk_check!(ips.CreateWaylandSurfaceKHR(
+                instance,
+                &create_info,
+                ptr::null(),
+                surface.as_mut_ptr()
+            ));
+            surface.assume_init()
+        }
+    }
+}
@@ -0,0 +1,393 @@
+use core::ffi::c_void;
+use core::mem::MaybeUninit;
+use core::ptr;
+
+use cty::{c_char, c_int, c_long, c_uint, c_ulong};
+
+use crate::log;
+use crate::platform::{Key, Platform, Surface};
+use crate::vk::{
+    Instance, InstancePtrs, SurfaceKHR, XlibSurfaceCreateFlagsKHR, XlibSurfaceCreateInfoKHR,
+    STRUCTURE_TYPE_XLIB_SURFACE_CREATE_INFO_KHR,
+};
+use crate::vk_check;
+
+pub enum Display {}
+pub type Window = c_ulong;
+pub type Atom = c_ulong;
+#[cfg(feature = "fullscreen")]
+pub type Pixmap = c_ulong;
+#[cfg(feature = "fullscreen")]
+pub type Cursor = c_ulong;
+pub type KeySym = c_ulong;
+pub type Bool = c_int;
+
+const KEY_PRESS: c_int = 2;
+const KEY_RELEASE: c_int = 3;
+const UNMAP_NOTIFY: c_int = 18;
+const MAP_NOTIFY: c_int = 19;
+const CONFIGURE_NOTIFY: c_int = 22;
+const CLIENT_MESSAGE: c_int = 33;
+const KEY_PRESS_MASK: c_long = 1 << 0;
+const KEY_RELEASE_MASK: c_long = 1 << 1;
+const STRUCTURE_NOTIFY_MASK: c_long = 1 << 17;
+#[cfg(feature = "fullscreen")]
+const XA_ATOM: Atom = 4;
+#[cfg(feature = "fullscreen")]
+const PROP_MODE_REPLACE: c_int = 0;
+const XK_ESCAPE: KeySym = 0xff1b;
+const BUTTON1_MASK: c_uint = 1 << 8;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XKeyEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub root: Window,
+    pub subwindow: Window,
+    pub time: c_ulong,
+    pub x: c_int,
+    pub y: c_int,
+    pub x_root: c_int,
+    pub y_root: c_int,
+    pub state: c_uint,
+    pub keycode: c_uint,
+    pub same_screen: Bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XClientMessageEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub window: Window,
+    pub message_type: Atom,
+    pub format: c_int,
+    pub data: [c_long; 5],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct XConfigureEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: Bool,
+    pub display: *mut Display,
+    pub event: Window,
+    pub window: Window,
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_int,
+    pub height: c_int,
+    pub border_width: c_int,
+    pub above: Window,
+    pub override_redirect: Bool,
+}
+
+#[cfg(feature = "fullscreen")]
+#[repr(C)]
+pub struct XColor {
+    pub pixel: c_ulong,
+    pub red: u16,
+    pub green: u16,
+    pub blue: u16,
+    pub flags: c_char,
+    pub pad: c_char,
+}
+
+#[repr(C)]
+pub union XEvent {
+    pub type_: c_int,
+    pub key: XKeyEvent,
+    pub configure: XConfigureEvent,
+    pub client_message: XClientMessageEvent,
+    pad: [c_long; 24],
+}
+
+#[link(name = "X11")]
+extern "C" {
+    fn XOpenDisplay(display_name: *const c_char) -> *mut Display;
+    fn XDefaultScreen(display: *mut Display) -> c_int;
+    fn XRootWindow(display: *mut Display, screen: c_int) -> Window;
+    #[cfg(feature = "fullscreen")]
+    fn XDisplayWidth(display: *mut Display, screen: c_int) -> c_int;
+    #[cfg(feature = "fullscreen")]
+    fn XDisplayHeight(display: *mut Display, screen: c_int) -> c_int;
+    fn XCreateSimpleWindow(
+        display: *mut Display,
+        parent: Window,
+        x: c_int,
+        y: c_int,
+        width: c_uint,
+        height: c_uint,
+        border_width: c_uint,
+        border: c_ulong,
+        background: c_ulong,
+    ) -> Window;
+    fn XStoreName(display: *mut Display, window: Window, name: *const c_char) -> c_int;
+    fn XSelectInput(display: *mut Display, window: Window, mask: c_long) -> c_int;
+    fn XInternAtom(display: *mut Display, name: *const c_char, only_if_exists: Bool) -> Atom;
+    fn XSetWMProtocols(
+        display: *mut Display,
+        window: Window,
+        protocols: *mut Atom,
+        count: c_int,
+    ) -> c_int;
+    #[cfg(feature = "fullscreen")]
+    fn XChangeProperty(
+        display: *mut Display,
+        window: Window,
+        property: Atom,
+        type_: Atom,
+        format: c_int,
+        mode: c_int,
+        data: *const u8,
+        nelements: c_int,
+    ) -> c_int;
+    #[cfg(feature = "fullscreen")]
+    fn XCreateBitmapFromData(
+        display: *mut Display,
+        drawable: Window,
+        data: *const c_char,
+        width: c_uint,
+        height: c_uint,
+    ) -> Pixmap;
+    #[cfg(feature = "fullscreen")]
+    fn XCreatePixmapCursor(
+        display: *mut Display,
+        source: Pixmap,
+        mask: Pixmap,
+        foreground: *mut XColor,
+        background: *mut XColor,
+        x: c_uint,
+        y: c_uint,
+    ) -> Cursor;
+    #[cfg(feature = "fullscreen")]
+    fn XDefineCursor(display: *mut Display, window: Window, cursor: Cursor) -> c_int;
+    fn XMapWindow(display: *mut Display, window: Window) -> c_int;
+    fn XPending(display: *mut Display) -> c_int;
+    fn XNextEvent(display: *mut Display, event: *mut XEvent) -> c_int;
+    fn XPeekEvent(display: *mut Display, event: *mut XEvent) -> c_int;
+    fn XLookupKeysym(event: *mut XKeyEvent, index: c_int) -> KeySym;
+    fn XQueryPointer(
+        display: *mut Display,
+        window: Window,
+        root: *mut Window,
+        child: *mut Window,
+        root_x: *mut c_int,
+        root_y: *mut c_int,
+        window_x: *mut c_int,
+        window_y: *mut c_int,
+        mask: *mut c_uint,
+    ) -> Bool;
+}
+
+#[derive(Clone, Copy)]
+pub struct XWindow {
+    pub display: *mut Display,
+    pub window: Window,
+    wm_delete_window: Atom,
+    escape: bool,
+    mapped: bool,
+    width: c_uint,
+    height: c_uint,
+}
+
+pub fn create_window() -> XWindow {
+    unsafe {
+        let display = XOpenDisplay(ptr::null());
+        if display.is_null() {
+            log!("Can't open the X display, is DISPLAY set?\n");
+            XWindow::exit(1);
+        }
+        let screen = XDefaultScreen(display);
+        let root = XRootWindow(display, screen);
+
+        #[cfg(feature = "fullscreen")]
+        let (width, height) = (
+            XDisplayWidth(display, screen) as c_uint,
+            XDisplayHeight(display, screen) as c_uint,
+        );
+        #[cfg(not(feature = "fullscreen"))]
+        let (width, height) = crate::platform::REQUESTED_SIZE;
+
+        let window = XCreateSimpleWindow(display, root, 0, 0, width, height, 0, 0, 0);
+        XStoreName(display, window, "GLWIN\0".as_ptr() as *const c_char);
+        XSelectInput(
+            display,
+            window,
+            KEY_PRESS_MASK | KEY_RELEASE_MASK | STRUCTURE_NOTIFY_MASK,
+        );
+
+        // Ask the window manager to send us a ClientMessage instead of killing
+        // the connection when the close button is pressed.
+        let mut wm_delete_window =
+            XInternAtom(display, "WM_DELETE_WINDOW\0".as_ptr() as *const c_char, 0);
+        XSetWMProtocols(display, window, &mut wm_delete_window, 1);
+
+        #[cfg(feature = "fullscreen")]
+        {
+            // EWMH fullscreen has to be set before the window is mapped,
+            // otherwise it needs a ClientMessage round trip to the WM.
+            let wm_state = XInternAtom(display, "_NET_WM_STATE\0".as_ptr() as *const c_char, 0);
+            let fullscreen = XInternAtom(
+                display,
+                "_NET_WM_STATE_FULLSCREEN\0".as_ptr() as *const c_char,
+                0,
+            );
+            XChangeProperty(
+                display,
+                window,
+                wm_state,
+                XA_ATOM,
+                32,
+                PROP_MODE_REPLACE,
+                &fullscreen as *const Atom as *const u8,
+                1,
+            );
+
+            // X has no ShowCursor, so swap in an empty 1x1 cursor instead.
+            let mut black: XColor = core::mem::zeroed();
+            let blank =
+                XCreateBitmapFromData(display, window, "\0".as_ptr() as *const c_char, 1, 1);
+            let cursor = XCreatePixmapCursor(display, blank, blank, &mut black, &mut black, 0, 0);
+            XDefineCursor(display, window, cursor);
+        }
+
+        XMapWindow(display, window);
+
+        XWindow {
+            display,
+            window,
+            wm_delete_window,
+            escape: false,
+            mapped: false,
+            width,
+            height,
+        }
+    }
+}
+
+// Drain the X event queue without blocking. Returns false once the window has
+// been closed. Iconifying the window unmaps it, which is how we tell it has
+// been minimised.
+pub fn handle_message(window: &mut XWindow) -> bool {
+    unsafe {
+        while XPending(window.display) != 0 {
+            let mut event = MaybeUninit::<XEvent>::uninit();
+            XNextEvent(window.display, event.as_mut_ptr());
+            let mut event = event.assume_init();
+            match event.type_ {
+                KEY_PRESS | KEY_RELEASE => {
+                    if XLookupKeysym(&mut event.key, 0) == XK_ESCAPE {
+                        window.escape = event.type_ == KEY_PRESS;
+                    }
+                }
+                CONFIGURE_NOTIFY => {
+                    window.width = event.configure.width as c_uint;
+                    window.height = event.configure.height as c_uint;
+                }
+                MAP_NOTIFY => window.mapped = true,
+                UNMAP_NOTIFY => window.mapped = false,
+                CLIENT_MESSAGE => {
+                    if event.client_message.data[0] as Atom == window.wm_delete_window {
+                        return false;
+                    }
+                }
+                _ => {}
+            }
+        }
+        true
+    }
+}
+
+// Returns the pointer position relative to the window and the button mask.
+fn query_pointer(window: &XWindow) -> (c_int, c_int, c_uint) {
+    let (mut root, mut child) = (0, 0);
+    let (mut root_x, mut root_y, mut x, mut y, mut mask) = (0, 0, 0, 0, 0);
+    unsafe {
+        XQueryPointer(
+            window.display,
+            window.window,
+            &mut root,
+            &mut child,
+            &mut root_x,
+            &mut root_y,
+            &mut x,
+            &mut y,
+            &mut mask,
+        );
+    }
+    (x, y, mask)
+}
+
+impl Platform for XWindow {
+    const INSTANCE_EXTENSIONS: &'static [*const i8] = &[
+        "VK_KHR_surface\0".as_ptr() as *const i8,
+        "VK_KHR_xlib_surface\0".as_ptr() as *const i8,
+    ];
+
+    fn create_window() -> Self {
+        create_window()
+    }
+
+    fn pump_events(&mut self) -> bool {
+        handle_message(self)
+    }
+
+    // XPeekEvent blocks until an event arrives but leaves it on the queue.
+    fn wait_events(&mut self) {
+        let mut event = MaybeUninit::<XEvent>::uninit();
+        unsafe {
+            XPeekEvent(self.display, event.as_mut_ptr());
+        }
+    }
+
+    fn key_down(&self, key: Key) -> bool {
+        match key {
+            Key::Escape => self.escape,
+            Key::MouseLeft => query_pointer(self).2 & BUTTON1_MASK != 0,
+        }
+    }
+
+    fn mouse_position(&self) -> (i32, i32) {
+        let (x, y, _) = query_pointer(self);
+        (x, y)
+    }
+
+    fn size(&self) -> (u32, u32) {
+        if self.mapped {
+            (self.width, self.height)
+        } else {
+            (0, 0)
+        }
+    }
+}
+
+impl Surface for XWindow {
+    fn create_surface(&self, ips: &InstancePtrs, instance: Instance) -> SurfaceKHR {
+        let create_info = XlibSurfaceCreateInfoKHR {
+            sType: STRUCTURE_TYPE_XLIB_SURFACE_CREATE_INFO_KHR,
+            dpy: self.display as *mut c_void,
+            window: self.window,
+            pNext: ptr::null(),
+            flags: XlibSurfaceCreateFlagsKHR::EMPTY,
+        };
+
+        let mut surface = MaybeUninit::uninit();
+        unsafe {
+            vk_check!(ips.CreateXlibSurfaceKHR(
+                instance,
+                &create_info,
+                ptr::null(),
+                surface.as_mut_ptr()
+            ));
+            surface.assume_init()
+        }
+    }
+}
@@ -0,0 +1,850 @@
+// Host-side helpers for the intro. For now that is only the binding generator:
+//
+//     cargo xtask fetch
+//     cargo xtask vk [--check] [path/to/vk.xml]
+//
+// xtask/vk.xml is the part of the registry at REGISTRY_VERSION that the lists
+// below reach, checked in so CI can run --check without a network. fetch
+// replaces it with the whole registry, which a new command may need. vk reads it
+// (or the path it is given) and rewrites src/vk.rs, or with --check only fails
+// if src/vk.rs isn't what it would write. Only the commands listed in
+// xtask/commands.txt end up in the function tables, split into global, instance
+// and device level by what they dispatch on, and only the types those commands reach, plus anything named in
+// xtask/types.txt for structs that are only ever chained through pNext. The
+// table macro and the loader live in src/vk/loader.rs and are left alone.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::{env, fs};
+
+use roxmltree::{Document, Node};
+
+// The Vulkan-Docs release src/vk.rs is generated from. Bump it, fetch and
+// regenerate together so the checked in file always matches it.
+const REGISTRY_VERSION: &str = "v1.3.251";
+
+const HEADER: &str = "\
+// Generated by `cargo xtask vk` from the Khronos vk.xml registry. Don't edit
+// this by hand: list new commands in xtask/commands.txt, and structs that are
+// only reached through pNext in xtask/types.txt, then run it again.
+#![allow(non_upper_case_globals)]
+#![allow(non_snake_case)]
+#![allow(non_camel_case_types)]
+#![allow(unused)]
+
+use core::ffi::c_void;
+use cty::c_char;
+use cty::c_double;
+use cty::c_ulong;
+
+// The handle!, enumeration!, bitmask! and ptrs! macros, and the code that
+// fills the tables in.
+#[macro_use]
+mod loader;
+pub use self::loader::{init, load_device, load_instance};
+";
+
+fn main() {
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        Some("fetch") => fetch(&root()),
+        Some("vk") => {
+            let root = root();
+            let mut check = false;
+            let mut registry = None;
+            for arg in args {
+                match arg.as_str() {
+                    "--check" => check = true,
+                    _ => registry = Some(PathBuf::from(arg)),
+                }
+            }
+            let registry = registry.unwrap_or_else(|| root.join("xtask/vk.xml"));
+            generate(&root, &registry, check);
+        }
+        _ => {
+            eprintln!("usage: cargo xtask fetch | cargo xtask vk [--check] [path/to/vk.xml]");
+            process::exit(2);
+        }
+    }
+}
+
+fn root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .to_path_buf()
+}
+
+fn registry_url() -> String {
+    format!(
+        "https://raw.githubusercontent.com/KhronosGroup/Vulkan-Docs/{}/xml/vk.xml",
+        REGISTRY_VERSION
+    )
+}
+
+// curl ships with Windows 10 and every Linux distro, which saves pulling an
+// HTTP client into the xtask for one download.
+fn fetch(root: &Path) {
+    let output = root.join("xtask/vk.xml");
+    let status = Command::new("curl")
+        .args(["--fail", "--location", "--silent", "--show-error", "--output"])
+        .arg(&output)
+        .arg(registry_url())
+        .status()
+        .unwrap_or_else(|error| fail(&format!("can't run curl: {}", error)));
+    if !status.success() {
+        fail(&format!("downloading {} failed", registry_url()));
+    }
+    println!("wrote {} ({})", output.display(), REGISTRY_VERSION);
+}
+
+fn generate(root: &Path, registry: &Path, check: bool) {
+    let xml = fs::read_to_string(registry).unwrap_or_else(|error| {
+        fail(&format!(
+            "can't read {}: {}. Run `cargo xtask fetch` to download {}, or pass its path.",
+            registry.display(),
+            error,
+            registry_url()
+        ))
+    });
+    let document = Document::parse_with_options(
+        &xml,
+        roxmltree::ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        },
+    )
+    .unwrap_or_else(|error| fail(&format!("{}: {}", registry.display(), error)));
+    let commands = read_list(&root.join("xtask/commands.txt"));
+    let extra_types = read_list(&root.join("xtask/types.txt"));
+
+    let mut generator = Generator::new(document.root_element());
+    for name in &commands {
+        generator.visit_command(name);
+    }
+    for name in &extra_types {
+        generator.visit_type(&format!("Vk{}", name));
+    }
+    let source = format(generator.emit(&commands));
+
+    let output = root.join("src/vk.rs");
+    if check {
+        let current = fs::read_to_string(&output)
+            .unwrap_or_else(|error| fail(&format!("{}: {}", output.display(), error)));
+        if current != source {
+            fail(&format!(
+                "{} is out of date with {}, run `cargo xtask vk` and commit the result",
+                output.display(),
+                registry.display()
+            ));
+        }
+        println!("{} is up to date", output.display());
+        return;
+    }
+    fs::write(&output, source)
+        .unwrap_or_else(|error| fail(&format!("{}: {}", output.display(), error)));
+    println!(
+        "wrote {} ({} commands, {} types)",
+        output.display(),
+        commands.len(),
+        generator.reached.len()
+    );
+}
+
+// src/vk.rs is checked in formatted, so --check has to format what it compares
+// as well, and a missing rustfmt is an error either way.
+fn format(source: String) -> String {
+    let mut rustfmt = Command::new("rustfmt")
+        .args(["--edition", "2018", "--emit", "stdout"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|error| fail(&format!("can't run rustfmt: {}", error)));
+    rustfmt
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .unwrap_or_else(|error| fail(&format!("rustfmt: {}", error)));
+    let output = rustfmt
+        .wait_with_output()
+        .unwrap_or_else(|error| fail(&format!("rustfmt: {}", error)));
+    if !output.status.success() {
+        fail("rustfmt failed on the generated source");
+    }
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1)
+}
+
+// One name per line, `#` starts a comment.
+fn read_list(path: &Path) -> Vec<String> {
+    fs::read_to_string(path)
+        .unwrap_or_else(|error| fail(&format!("{}: {}", path.display(), error)))
+        .lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+// Elements tagged for Vulkan SC only have no business in a desktop build.
+fn for_vulkan(node: Node) -> bool {
+    match node.attribute("api") {
+        Some(api) => api.split(',').any(|api| api == "vulkan"),
+        None => true,
+    }
+}
+
+fn supported(node: Node) -> bool {
+    match node.attribute("supported") {
+        Some(supported) => supported.split(',').any(|api| api == "vulkan"),
+        None => true,
+    }
+}
+
+fn strip_type(name: &str) -> &str {
+    name.strip_prefix("Vk").unwrap_or(name)
+}
+
+fn strip_constant(name: &str) -> &str {
+    name.strip_prefix("VK_").unwrap_or(name)
+}
+
+fn field_name(name: &str) -> &str {
+    match name {
+        "type" => "ty",
+        name => name,
+    }
+}
+
+// The text of an element with comments left out, which is all a member,
+// param or funcpointer declaration is once the markup is gone.
+fn declaration_text(node: Node) -> String {
+    let mut text = String::new();
+    for child in node.children() {
+        if child.has_tag_name("comment") {
+            continue;
+        }
+        if child.is_text() {
+            text.push_str(child.text().unwrap());
+        } else {
+            text.push_str(&declaration_text(child));
+        }
+    }
+    text
+}
+
+// A C declaration taken apart, e.g. `const char* const* ppEnabledLayerNames`
+// or `float matrix[3][4]`.
+struct Declaration {
+    name: String,
+    base: String,
+    // One entry per `*`, innermost first: whether what it points at is const.
+    pointers: Vec<bool>,
+    arrays: Vec<String>,
+    bitfield: bool,
+}
+
+fn parse_declaration(text: &str) -> Declaration {
+    let text = text.trim().trim_end_matches(';').trim_end_matches(',');
+    let (text, bitfield) = match text.find(':') {
+        Some(colon) => (&text[..colon], true),
+        None => (text, false),
+    };
+    let (text, arrays) = match text.find('[') {
+        Some(bracket) => {
+            let arrays = text[bracket..]
+                .split(['[', ']'])
+                .map(str::trim)
+                .filter(|size| !size.is_empty())
+                .map(String::from)
+                .collect();
+            (&text[..bracket], arrays)
+        }
+        None => (text, Vec::new()),
+    };
+    let spaced = text.replace('*', " * ");
+    let mut tokens: Vec<&str> = spaced.split_whitespace().collect();
+    let name = tokens.pop().unwrap_or_default().to_string();
+    let mut base = String::new();
+    let mut pointers = Vec::new();
+    let mut pointee_const = false;
+    for token in tokens {
+        match token {
+            "struct" => {}
+            "const" => pointee_const = true,
+            "*" => {
+                pointers.push(pointee_const);
+                pointee_const = false;
+            }
+            token => base = token.to_string(),
+        }
+    }
+    Declaration {
+        name,
+        base,
+        pointers,
+        arrays,
+        bitfield,
+    }
+}
+
+fn c_type(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "void" => "c_void",
+        "char" => "c_char",
+        "float" => "f32",
+        "double" => "c_double",
+        "int8_t" => "i8",
+        "uint8_t" => "u8",
+        "int16_t" => "i16",
+        "uint16_t" => "u16",
+        "int" | "int32_t" => "i32",
+        "uint32_t" => "u32",
+        "int64_t" => "i64",
+        "uint64_t" => "u64",
+        "size_t" => "usize",
+        // Window system types, only ever handed through to the driver.
+        "Display" | "wl_display" | "wl_surface" | "xcb_connection_t" | "SECURITY_ATTRIBUTES" => {
+            "c_void"
+        }
+        "Window" | "VisualID" => "c_ulong",
+        "xcb_window_t" | "xcb_visualid_t" | "DWORD" => "u32",
+        "HINSTANCE" | "HWND" | "HMONITOR" | "HANDLE" => "*mut c_void",
+        "LPCWSTR" => "*const u16",
+        _ => return None,
+    })
+}
+
+// Integer literal for an enum value, written the way the old bindings did.
+// Extension values are numbered from the extension they came from.
+// Values come out as a `ty(...)` literal, aliases as the bare constant name.
+fn enum_value(value: Node, number: Option<i64>, ty: &str, wide: bool) -> Option<String> {
+    if let Some(offset) = value.attribute("offset") {
+        let extension = value
+            .attribute("extnumber")
+            .and_then(|n| n.parse().ok())
+            .or(number)
+            .unwrap_or_else(|| fail("enum offset outside of an extension"));
+        let offset: i64 = offset.parse().unwrap();
+        let magnitude = 1_000_000_000 + (extension - 1) * 1000 + offset;
+        return Some(match value.attribute("dir") {
+            Some("-") => format!("{}(-{}i32 as u32)", ty, magnitude),
+            _ => format!("{}({})", ty, magnitude),
+        });
+    }
+    if let Some(bitpos) = value.attribute("bitpos") {
+        let bit: u64 = 1 << bitpos.parse::<u32>().unwrap();
+        return Some(if wide {
+            format!("{}(0x{:016x})", ty, bit)
+        } else {
+            format!("{}(0x{:08x})", ty, bit)
+        });
+    }
+    if let Some(literal) = value.attribute("value") {
+        let literal = literal.trim();
+        return Some(match literal.strip_prefix('-') {
+            Some(_) => format!("{}({}i32 as u32)", ty, literal),
+            None => format!("{}({})", ty, literal),
+        });
+    }
+    value
+        .attribute("alias")
+        .map(|alias| strip_constant(alias).to_string())
+}
+
+fn constant_value(value: &str) -> (&'static str, String) {
+    let value = value.trim().trim_start_matches('(').trim_end_matches(')');
+    if let Some(inverted) = value.strip_prefix('~') {
+        let digits = inverted.trim_end_matches(['U', 'L']);
+        let ty = if inverted.ends_with("ULL") {
+            "u64"
+        } else {
+            "u32"
+        };
+        return (ty, format!("!{}", digits));
+    }
+    if value.contains('.') {
+        return ("f32", value.trim_end_matches(['f', 'F']).to_string());
+    }
+    ("u32", value.trim_end_matches('U').to_string())
+}
+
+struct Generator<'a, 'input> {
+    root: Node<'a, 'input>,
+    types: HashMap<&'a str, Node<'a, 'input>>,
+    enums: HashMap<&'a str, Node<'a, 'input>>,
+    commands: HashMap<&'a str, Node<'a, 'input>>,
+    // Values features and extensions add to an existing enum, by enum name.
+    // The extension number is None for core features.
+    extensions: HashMap<&'a str, Vec<(Node<'a, 'input>, Option<i64>)>>,
+    reached: HashSet<String>,
+}
+
+impl<'a, 'input> Generator<'a, 'input> {
+    fn new(root: Node<'a, 'input>) -> Self {
+        let mut generator = Generator {
+            root,
+            types: HashMap::new(),
+            enums: HashMap::new(),
+            commands: HashMap::new(),
+            extensions: HashMap::new(),
+            reached: HashSet::new(),
+        };
+        for block in root.children().filter(|node| node.is_element()) {
+            match block.tag_name().name() {
+                "types" => {
+                    for ty in block.children().filter(|node| node.has_tag_name("type")) {
+                        if !for_vulkan(ty) {
+                            continue;
+                        }
+                        if let Some(name) = type_name(ty) {
+                            generator.types.insert(name, ty);
+                        }
+                    }
+                }
+                "enums" => {
+                    if let Some(name) = block.attribute("name") {
+                        generator.enums.insert(name, block);
+                    }
+                }
+                "commands" => {
+                    for command in block.children().filter(|node| node.has_tag_name("command")) {
+                        if !for_vulkan(command) {
+                            continue;
+                        }
+                        let name = command.attribute("name").or_else(|| {
+                            command
+                                .children()
+                                .find(|node| node.has_tag_name("proto"))
+                                .and_then(|proto| child_text(proto, "name"))
+                        });
+                        if let Some(name) = name {
+                            generator.commands.insert(name, command);
+                        }
+                    }
+                }
+                "feature" if for_vulkan(block) => generator.collect_extensions(block, None),
+                "extensions" => {
+                    for extension in block
+                        .children()
+                        .filter(|node| node.has_tag_name("extension"))
+                    {
+                        if supported(extension) {
+                            let number = extension.attribute("number").and_then(|n| n.parse().ok());
+                            generator.collect_extensions(extension, number);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        generator
+    }
+
+    fn collect_extensions(&mut self, block: Node<'a, 'input>, number: Option<i64>) {
+        for require in block.children().filter(|node| node.has_tag_name("require")) {
+            if !for_vulkan(require) {
+                continue;
+            }
+            for value in require.children().filter(|node| node.has_tag_name("enum")) {
+                if let Some(extends) = value.attribute("extends") {
+                    self.extensions
+                        .entry(extends)
+                        .or_default()
+                        .push((value, number));
+                }
+            }
+        }
+    }
+
+    fn visit_command(&mut self, name: &str) {
+        let command = self.command(name);
+        if let Some(proto) = command.children().find(|node| node.has_tag_name("proto")) {
+            self.visit_type(&parse_declaration(&declaration_text(proto)).base);
+        }
+        for param in params(command) {
+            self.visit_type(&parse_declaration(&declaration_text(param)).base);
+        }
+    }
+
+    // Follows aliases to the command that actually has a prototype.
+    fn command(&self, name: &str) -> Node<'a, 'input> {
+        let full = format!("vk{}", name);
+        let mut command = *self
+            .commands
+            .get(full.as_str())
+            .unwrap_or_else(|| fail(&format!("vk.xml has no command {}", full)));
+        while let Some(alias) = command.attribute("alias") {
+            command = self.commands[alias];
+        }
+        command
+    }
+
+    fn visit_type(&mut self, name: &str) {
+        if c_type(name).is_some() || !self.reached.insert(name.to_string()) {
+            return;
+        }
+        let ty = *self
+            .types
+            .get(name)
+            .unwrap_or_else(|| fail(&format!("vk.xml has no type {}", name)));
+        if let Some(alias) = ty.attribute("alias") {
+            return self.visit_type(alias);
+        }
+        match ty.attribute("category") {
+            Some("struct") | Some("union") => {
+                for member in members(ty) {
+                    let declaration = parse_declaration(&declaration_text(member));
+                    if declaration.bitfield {
+                        fail(&format!("{} has bitfields, which aren't supported", name));
+                    }
+                    self.visit_type(&declaration.base);
+                }
+            }
+            Some("funcpointer") => {
+                let (result, params) = funcpointer(ty);
+                self.visit_type(&result.base);
+                for param in params {
+                    self.visit_type(&param.base);
+                }
+            }
+            Some("bitmask") => {
+                if let Some(base) = child_text(ty, "type") {
+                    self.visit_type(base);
+                }
+                if let Some(bits) = ty.attribute("requires").or(ty.attribute("bitvalues")) {
+                    self.visit_type(bits);
+                }
+            }
+            Some("basetype") => {
+                if let Some(base) = child_text(ty, "type") {
+                    self.visit_type(base);
+                }
+            }
+            // FlagBits are emitted as an alias of their mask, so that has to
+            // exist even when only the bits were asked for.
+            Some("enum") => {
+                if let Some(mask) = self.mask_of(name) {
+                    self.visit_type(mask);
+                }
+            }
+            Some("handle") => {}
+            _ => fail(&format!("no Rust mapping for {}", name)),
+        }
+    }
+
+    fn rust_type(&self, declaration: &Declaration) -> String {
+        let mut ty = match c_type(&declaration.base) {
+            Some(ty) => ty.to_string(),
+            None => strip_type(&declaration.base).to_string(),
+        };
+        for &pointee_const in &declaration.pointers {
+            ty = format!("*{} {}", if pointee_const { "const" } else { "mut" }, ty);
+        }
+        for size in declaration.arrays.iter().rev() {
+            ty = match size.parse::<usize>() {
+                Ok(size) => format!("[{}; {}]", ty, size),
+                Err(_) => format!("[{}; {} as usize]", ty, strip_constant(size)),
+            };
+        }
+        ty
+    }
+
+    fn emit(&self, commands: &[String]) -> String {
+        let mut out = String::from(HEADER);
+        self.emit_api_constants(&mut out);
+        let types = self
+            .root
+            .children()
+            .filter(|node| node.has_tag_name("types"))
+            .flat_map(|block| block.children())
+            .filter(|node| node.has_tag_name("type") && for_vulkan(*node));
+        for ty in types {
+            let name = match type_name(ty) {
+                Some(name) if self.reached.contains(name) => name,
+                _ => continue,
+            };
+            if let Some(alias) = ty.attribute("alias") {
+                writeln!(
+                    out,
+                    "\npub type {} = {};",
+                    strip_type(name),
+                    strip_type(alias)
+                )
+                .unwrap();
+                continue;
+            }
+            match ty.attribute("category") {
+                Some("basetype") => self.emit_basetype(&mut out, ty, name),
+                Some("bitmask") => {
+                    let base = child_text(ty, "type").unwrap_or("VkFlags");
+                    writeln!(
+                        out,
+                        "\nbitmask!({}, {});",
+                        strip_type(name),
+                        strip_type(base)
+                    )
+                    .unwrap();
+                }
+                Some("handle") => {
+                    if child_text(ty, "type") == Some("VK_DEFINE_HANDLE") {
+                        writeln!(out, "\npub type {} = usize;", strip_type(name)).unwrap();
+                    } else {
+                        writeln!(out, "\nhandle!({});", strip_type(name)).unwrap();
+                    }
+                }
+                Some("enum") => self.emit_enum(&mut out, name),
+                Some("funcpointer") => {
+                    let (result, params) = funcpointer(ty);
+                    let params: Vec<String> = params.iter().map(|p| self.rust_type(p)).collect();
+                    write!(
+                        out,
+                        "\npub type {} = extern \"system\" fn({})",
+                        name,
+                        params.join(", ")
+                    )
+                    .unwrap();
+                    if result.base != "void" || !result.pointers.is_empty() {
+                        write!(out, " -> {}", self.rust_type(&result)).unwrap();
+                    }
+                    out.push_str(";\n");
+                }
+                Some(kind @ "struct") | Some(kind @ "union") => {
+                    writeln!(out, "\n#[repr(C)]\n#[derive(Clone, Copy)]").unwrap();
+                    writeln!(out, "pub {} {} {{", kind, strip_type(name)).unwrap();
+                    for member in members(ty) {
+                        let declaration = parse_declaration(&declaration_text(member));
+                        let ty = self.rust_type(&declaration);
+                        writeln!(out, "    pub {}: {},", field_name(&declaration.name), ty)
+                            .unwrap();
+                    }
+                    out.push_str("}\n");
+                }
+                _ => {}
+            }
+        }
+        self.emit_tables(&mut out, commands);
+        out
+    }
+
+    fn emit_api_constants(&self, out: &mut String) {
+        let constants = match self.enums.get("API Constants") {
+            Some(constants) => constants,
+            None => return,
+        };
+        out.push('\n');
+        for constant in constants
+            .children()
+            .filter(|node| node.has_tag_name("enum"))
+        {
+            let name = strip_constant(constant.attribute("name").unwrap());
+            if let Some(alias) = constant.attribute("alias") {
+                let target = constants
+                    .children()
+                    .find(|node| node.attribute("name") == Some(alias))
+                    .and_then(|node| node.attribute("value"))
+                    .unwrap_or("0");
+                let (ty, _) = constant_value(target);
+                writeln!(
+                    out,
+                    "pub const {}: {} = {};",
+                    name,
+                    ty,
+                    strip_constant(alias)
+                )
+                .unwrap();
+                continue;
+            }
+            let (mut ty, value) = constant_value(constant.attribute("value").unwrap());
+            if let Some(declared) = constant.attribute("type") {
+                ty = c_type(declared).unwrap_or(ty);
+            }
+            writeln!(out, "pub const {}: {} = {};", name, ty, value).unwrap();
+        }
+    }
+
+    fn emit_basetype(&self, out: &mut String, ty: Node, name: &str) {
+        let repr = match child_text(ty, "type") {
+            Some(base) => c_type(base)
+                .map(String::from)
+                .unwrap_or_else(|| strip_type(base).into()),
+            // Opaque platform structs, only ever seen behind a pointer.
+            None => "c_void".to_string(),
+        };
+        writeln!(out, "\npub type {} = {};", strip_type(name), repr).unwrap();
+    }
+
+    // FlagBits enums become an alias of the bitmask that uses them, so bits and
+    // masks are one type. Everything else gets a type of its own.
+    fn emit_enum(&self, out: &mut String, name: &str) {
+        let block = self.enums.get(name);
+        let wide = block.and_then(|block| block.attribute("bitwidth")) == Some("64");
+        let bits = block.and_then(|block| block.attribute("type")) == Some("bitmask");
+        let ty = if bits {
+            match self.mask_of(name) {
+                Some(mask) => {
+                    let mask = strip_type(mask);
+                    writeln!(out, "\npub type {} = {};", strip_type(name), mask).unwrap();
+                    mask
+                }
+                None => {
+                    let base = if wide { "Flags64" } else { "Flags" };
+                    writeln!(out, "\nbitmask!({}, {});", strip_type(name), base).unwrap();
+                    strip_type(name)
+                }
+            }
+        } else {
+            writeln!(out, "\nenumeration!({});", strip_type(name)).unwrap();
+            strip_type(name)
+        };
+        let core = block
+            .into_iter()
+            .flat_map(|block| block.children())
+            .filter(|node| node.has_tag_name("enum"))
+            .map(|value| (value, None));
+        let extended = self.extensions.get(name).into_iter().flatten().copied();
+        // Promoted extensions list the same value again in the core feature.
+        let mut seen = HashSet::new();
+        for (value, number) in core.chain(extended) {
+            let constant = strip_constant(value.attribute("name").unwrap());
+            if !for_vulkan(value) || !seen.insert(constant) {
+                continue;
+            }
+            if let Some(literal) = enum_value(value, number, ty, wide) {
+                writeln!(out, "pub const {}: {} = {};", constant, ty, literal).unwrap();
+            }
+        }
+    }
+
+    // The bitmask type whose bits are the FlagBits enum `bits`.
+    fn mask_of(&self, bits: &str) -> Option<&'a str> {
+        self.types
+            .iter()
+            .find(|(_, ty)| {
+                ty.attribute("category") == Some("bitmask")
+                    && ty.attribute("requires").or(ty.attribute("bitvalues")) == Some(bits)
+            })
+            .map(|(&name, _)| name)
+    }
+
+    fn emit_tables(&self, out: &mut String, commands: &[String]) {
+        for table in &["EntryPtrs", "InstancePtrs", "DevicePtrs"] {
+            writeln!(out, "\nptrs!({}, {{", table).unwrap();
+            for name in commands.iter().filter(|name| self.table(name) == *table) {
+                self.emit_command(out, name);
+            }
+            out.push_str("});\n");
+        }
+    }
+
+    // Which table a command goes in, going by what it dispatches on.
+    // vkGetDeviceProcAddr takes a device but is what loads the device table, so
+    // it sits with the instance commands.
+    fn table(&self, name: &str) -> &'static str {
+        let first = params(self.command(name))
+            .next()
+            .map(|param| parse_declaration(&declaration_text(param)).base);
+        match (name, first.as_deref()) {
+            ("GetInstanceProcAddr", _) => "EntryPtrs",
+            ("GetDeviceProcAddr", _) => "InstancePtrs",
+            (_, Some("VkInstance")) | (_, Some("VkPhysicalDevice")) => "InstancePtrs",
+            (_, Some("VkDevice")) | (_, Some("VkQueue")) | (_, Some("VkCommandBuffer")) => {
+                "DevicePtrs"
+            }
+            _ => "EntryPtrs",
+        }
+    }
+
+    fn emit_command(&self, out: &mut String, name: &str) {
+        let command = self.command(name);
+        let proto = command
+            .children()
+            .find(|node| node.has_tag_name("proto"))
+            .unwrap();
+        let result = parse_declaration(&declaration_text(proto));
+        let params: Vec<String> = params(command)
+            .map(|param| {
+                let declaration = parse_declaration(&declaration_text(param));
+                format!(
+                    "{}: {}",
+                    field_name(&declaration.name),
+                    self.rust_type(&declaration)
+                )
+            })
+            .collect();
+        // The loader null checks what comes back, which an fn type can't be.
+        let result = match result.base.as_str() {
+            "PFN_vkVoidFunction" => "*const c_void".to_string(),
+            "void" if result.pointers.is_empty() => "()".to_string(),
+            _ => self.rust_type(&result),
+        };
+        writeln!(
+            out,
+            "    {} => ({}) -> {},",
+            name,
+            params.join(", "),
+            result
+        )
+        .unwrap();
+    }
+}
+
+fn type_name<'a>(ty: Node<'a, '_>) -> Option<&'a str> {
+    ty.attribute("name").or_else(|| child_text(ty, "name"))
+}
+
+fn child_text<'a>(node: Node<'a, '_>, tag: &str) -> Option<&'a str> {
+    node.children()
+        .find(|child| child.has_tag_name(tag))
+        .and_then(|child| child.text())
+}
+
+fn members<'a, 'input>(ty: Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    ty.children()
+        .filter(|node| node.has_tag_name("member") && for_vulkan(*node))
+}
+
+fn params<'a, 'input>(command: Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    command
+        .children()
+        .filter(|node| node.has_tag_name("param") && for_vulkan(*node))
+}
+
+// Older registries spell funcpointers out as a C typedef, newer ones give them
+// the same proto/param markup as commands.
+fn funcpointer(ty: Node) -> (Declaration, Vec<Declaration>) {
+    if let Some(proto) = ty.children().find(|node| node.has_tag_name("proto")) {
+        let params = ty
+            .children()
+            .filter(|node| node.has_tag_name("param"))
+            .map(|param| parse_declaration(&declaration_text(param)))
+            .collect();
+        return (parse_declaration(&declaration_text(proto)), params);
+    }
+    let text = declaration_text(ty);
+    let result = text
+        .trim_start()
+        .trim_start_matches("typedef")
+        .split("(VKAPI_PTR")
+        .next()
+        .unwrap();
+    let params = text
+        .rsplit(")(")
+        .next()
+        .unwrap()
+        .trim_end()
+        .trim_end_matches(");");
+    let params = match params.trim() {
+        "void" => Vec::new(),
+        params => params.split(',').map(parse_declaration).collect(),
+    };
+    (parse_declaration(&format!("{} result", result)), params)
+}